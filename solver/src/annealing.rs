@@ -0,0 +1,870 @@
+// 焼きなまし (山登り) の共通部分
+// 状態(State)と近傍(Move)を差し替えて、受理条件・温度・時間を設定で切り替える
+use crate::budget::Budget;
+use crate::checkpoint::{BestOutput, Checkpoint};
+use crate::problem::{Input, Score, Solution, MAX_VOLUME};
+use anyhow::{bail, Result};
+use geo::Point;
+use rand::Rng;
+use rand_pcg::Pcg64Mcg;
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+// 1人分の変更。変更後の位置とvolume
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Change {
+    pub musician: usize,
+    pub position: Point,
+    pub volume: f64,
+}
+
+// 近傍が提案する差分。変わるmusicianについてだけ変更後の位置とvolumeを持つ
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Delta {
+    pub changes: Vec<Change>,
+}
+
+impl Delta {
+    // volumeはそのままで動かす
+    pub fn relocate<S: State + ?Sized>(state: &S, moves: &[(usize, Point)]) -> Self {
+        Delta {
+            changes: moves
+                .iter()
+                .map(|&(musician, position)| Change {
+                    musician,
+                    position,
+                    volume: state.volume(musician),
+                })
+                .collect(),
+        }
+    }
+
+    pub fn swap<S: State + ?Sized>(state: &S, i: usize, j: usize) -> Self {
+        let placements = state.placements();
+        Self::relocate(state, &[(i, placements[j]), (j, placements[i])])
+    }
+
+    pub fn volume<S: State + ?Sized>(state: &S, musician: usize, volume: f64) -> Self {
+        Delta {
+            changes: vec![Change {
+                musician,
+                position: state.placements()[musician],
+                volume,
+            }],
+        }
+    }
+
+    // musicianのvolumeも変える。まだ含まれていなければ追加する
+    pub fn set_volume<S: State + ?Sized>(&mut self, state: &S, musician: usize, volume: f64) {
+        match self.changes.iter_mut().find(|c| c.musician == musician) {
+            Some(c) => c.volume = volume,
+            None => self
+                .changes
+                .extend(Self::volume(state, musician, volume).changes),
+        }
+    }
+
+    // 変更するmusician
+    pub fn touched(&self) -> impl Iterator<Item = usize> + '_ {
+        self.changes.iter().map(|c| c.musician)
+    }
+
+    // 位置が変わるものだけ
+    pub fn moves<S: State + ?Sized>(&self, state: &S) -> Vec<(usize, Point)> {
+        self.changes
+            .iter()
+            .filter(|c| c.position != state.placements()[c.musician])
+            .map(|c| (c.musician, c.position))
+            .collect()
+    }
+
+    // stateに適用しても正しい解のままか
+    pub fn is_valid<S: State + ?Sized>(&self, state: &S) -> bool {
+        self.changes
+            .iter()
+            .all(|c| (0.0..=MAX_VOLUME).contains(&c.volume))
+            && state
+                .input()
+                .is_valid_relocation(state.placements(), &self.moves(state))
+    }
+
+    // solutionに適用し、元に戻すための差分を返す
+    pub fn apply_to(&self, solution: &mut Solution) -> Delta {
+        let mut inverse = Delta::default();
+        for c in self.changes.iter() {
+            inverse.changes.push(Change {
+                musician: c.musician,
+                position: solution.placements[c.musician],
+                volume: solution.volume(c.musician),
+            });
+        }
+        for c in self.changes.iter() {
+            solution.placements[c.musician] = c.position;
+            if solution.volume(c.musician) != c.volume {
+                solution.set_volume(c.musician, c.volume);
+            }
+        }
+        inverse.changes.reverse();
+        inverse
+    }
+}
+
+// 焼きなましで動かす状態。Deltaを適用し、受理ならcommit、棄却ならrollbackする
+pub trait State {
+    fn input(&self) -> &Input;
+    fn placements(&self) -> &[Point];
+    fn volume(&self, musician_id: usize) -> f64;
+    fn score(&self) -> Score;
+    // 不正な配置やvolumeになるなら何もせずfalseを返す
+    fn apply(&mut self, delta: &Delta) -> bool;
+    // commitしてからの変更を確定する
+    fn commit(&mut self);
+    // commitしてからの変更をすべて取り消す
+    fn rollback(&mut self);
+    fn solution(&self) -> Solution;
+}
+
+// 近傍。今の状態からの差分を提案し、作れなければNoneを返す
+// tempは現在の温度で、移動幅を温度で変えたいとき用
+pub trait Move {
+    fn name(&self) -> &str;
+    fn propose(&mut self, state: &dyn State, temp: f64, rng: &mut Pcg64Mcg) -> Option<Delta>;
+}
+
+// Solutionをそのまま持ち、変更のたびにscore_fastで計算し直す
+pub struct SolutionState<'a> {
+    input: &'a Input,
+    solution: Solution,
+    score: Score,
+    // commitしてから適用した差分を戻すためのものと、適用前のスコア
+    history: Vec<(Delta, Score)>,
+}
+
+impl<'a> SolutionState<'a> {
+    pub fn new(input: &'a Input, solution: Solution) -> Result<Self> {
+        let score = input.score_fast(&solution)?;
+        Ok(SolutionState {
+            input,
+            solution,
+            score,
+            history: vec![],
+        })
+    }
+}
+
+impl State for SolutionState<'_> {
+    fn input(&self) -> &Input {
+        self.input
+    }
+
+    fn placements(&self) -> &[Point] {
+        &self.solution.placements
+    }
+
+    fn volume(&self, musician_id: usize) -> f64 {
+        self.solution.volume(musician_id)
+    }
+
+    fn score(&self) -> Score {
+        self.score
+    }
+
+    fn apply(&mut self, delta: &Delta) -> bool {
+        if !delta.is_valid(self) {
+            return false;
+        }
+        let inverse = delta.apply_to(&mut self.solution);
+        let score = self.input.score_fast(&self.solution).unwrap();
+        self.history
+            .push((inverse, std::mem::replace(&mut self.score, score)));
+        true
+    }
+
+    fn commit(&mut self) {
+        self.history.clear();
+    }
+
+    fn rollback(&mut self) {
+        while let Some((inverse, score)) = self.history.pop() {
+            inverse.apply_to(&mut self.solution);
+            self.score = score;
+        }
+    }
+
+    fn solution(&self) -> Solution {
+        self.solution.clone()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Acceptance {
+    // 良くなったときだけ受理する (山登り)
+    Greedy,
+    // 悪化してもexp(差 / 温度)の確率で受理する
+    Metropolis,
+    // 今か、指定回数前のスコア以上なら受理する
+    LateAcceptance(usize),
+    // 悪化が温度未満なら受理する
+    Threshold,
+}
+
+const DEFAULT_LATE_ACCEPTANCE_LENGTH: usize = 1000;
+
+impl Acceptance {
+    fn accepts(
+        &self,
+        new_score: Score,
+        current: Score,
+        late: Option<Score>,
+        temp: f64,
+        rng: &mut Pcg64Mcg,
+    ) -> bool {
+        match *self {
+            Acceptance::Greedy => new_score > current,
+            Acceptance::Metropolis => {
+                new_score >= current
+                    || (temp > 0.0 && ((new_score - current) as f64 / temp).exp() > rng.gen())
+            }
+            Acceptance::LateAcceptance(_) => {
+                new_score >= current || late.map_or(false, |l| new_score >= l)
+            }
+            Acceptance::Threshold => ((current - new_score) as f64) < temp,
+        }
+    }
+}
+
+// "greedy", "metropolis", "late" (または "late:1000") , "threshold"
+impl FromStr for Acceptance {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "greedy" => Acceptance::Greedy,
+            "metropolis" => Acceptance::Metropolis,
+            "late" => Acceptance::LateAcceptance(DEFAULT_LATE_ACCEPTANCE_LENGTH),
+            "threshold" => Acceptance::Threshold,
+            _ => match s.strip_prefix("late:") {
+                Some(length) => match length.parse::<usize>() {
+                    Ok(length) if length > 0 => Acceptance::LateAcceptance(length),
+                    _ => bail!("invalid late acceptance length: {length}"),
+                },
+                None => bail!("unknown acceptance: {s}"),
+            },
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Schedule {
+    Constant(f64),
+    // 1回ごとにalpha倍し、minより下げない
+    Geometric { start: f64, alpha: f64, min: f64 },
+    // 経過時間に応じてstartからendへ指数的に下げる
+    Exponential { start: f64, end: f64 },
+    // 経過時間に応じてstartからendへ線形に下げる
+    Linear { start: f64, end: f64 },
+}
+
+impl Schedule {
+    // progressは経過時間の割合 (0.0..=1.0)
+    pub fn temperature(&self, iteration: u64, progress: f64) -> f64 {
+        let progress = progress.clamp(0.0, 1.0);
+        match *self {
+            Schedule::Constant(temp) => temp,
+            Schedule::Geometric { start, alpha, min } => {
+                (start * alpha.powf(iteration as f64)).max(min)
+            }
+            Schedule::Exponential { start, end } => start * (end / start).powf(progress),
+            Schedule::Linear { start, end } => start + (end - start) * progress,
+        }
+    }
+}
+
+// 近傍の選び方
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Selection {
+    // with_moveで指定した重みのまま選ぶ
+    Fixed,
+    // segment回ごとに、その間に良くした割合で重みを更新する
+    // reactionは新しい成績を重みにどれだけ反映するか、min_shareはすべての近傍に等分して残す確率
+    Adaptive {
+        segment: u64,
+        reaction: f64,
+        min_share: f64,
+    },
+}
+
+impl Selection {
+    pub const ADAPTIVE: Selection = Selection::Adaptive {
+        segment: 100,
+        reaction: 0.2,
+        min_share: 0.1,
+    };
+}
+
+impl FromStr for Selection {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "fixed" => Ok(Selection::Fixed),
+            "adaptive" => Ok(Selection::ADAPTIVE),
+            _ => bail!("unknown selection: {s}"),
+        }
+    }
+}
+
+// 適応的に選ぶときの1回あたりの報酬
+const REWARD_BEST: f64 = 1.0;
+const REWARD_IMPROVED: f64 = 0.5;
+const REWARD_ACCEPTED: f64 = 0.1;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Config {
+    pub acceptance: Acceptance,
+    pub schedule: Schedule,
+    pub selection: Selection,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MoveStats {
+    pub name: String,
+    pub tried: u64,
+    // stateを変更できなかった回数
+    pub failed: u64,
+    pub accepted: u64,
+    // 今のスコアより良くなった回数
+    pub improved: u64,
+    // 最良スコアを更新した回数
+    pub best_updates: u64,
+    // 今のスコアを良くした分の合計
+    pub gain: Score,
+    // 最後に選ばれていた確率
+    pub share: f64,
+}
+
+impl MoveStats {
+    fn rate(count: u64, tried: u64) -> f64 {
+        if tried == 0 {
+            0.0
+        } else {
+            100.0 * count as f64 / tried as f64
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Stats {
+    pub iterations: u64,
+    pub elapsed: Duration,
+    pub initial_score: Score,
+    pub best_score: Score,
+    pub moves: Vec<MoveStats>,
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "tried {} times in {:.1}s, score {} -> {}",
+            self.iterations,
+            self.elapsed.as_secs_f64(),
+            self.initial_score,
+            self.best_score
+        )?;
+        write!(
+            f,
+            "{:>12} {:>8} {:>7} {:>9} {:>9} {:>6} {:>14} {:>6}",
+            "move", "tried", "failed%", "accepted%", "improved%", "best", "gain", "share%"
+        )?;
+        for m in self.moves.iter() {
+            write!(
+                f,
+                "\n{:>12} {:>8} {:>7.1} {:>9.1} {:>9.1} {:>6} {:>14} {:>6.1}",
+                m.name,
+                m.tried,
+                MoveStats::rate(m.failed, m.tried),
+                MoveStats::rate(m.accepted, m.tried),
+                MoveStats::rate(m.improved, m.tried),
+                m.best_updates,
+                m.gain,
+                100.0 * m.share
+            )?;
+        }
+        Ok(())
+    }
+}
+
+// 最良解が良くなったことを知らせる最短の間隔。batchや並列のworkerで出力が埋まらないようにする
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+
+pub struct Annealer<'a> {
+    config: Config,
+    moves: Vec<Box<dyn Move + 'a>>,
+    // with_moveで指定した重み
+    base_weights: Vec<f64>,
+    // 今の重み。Adaptiveなら成績に応じて変わる
+    weights: Vec<f64>,
+    // 今のsegmentでの(選ばれた回数, 報酬の合計)
+    segment: Vec<(u64, f64)>,
+    stats: Stats,
+    // (書き出し先, 間隔)
+    checkpoint: Option<(String, Duration)>,
+    output: Option<&'a BestOutput<'a>>,
+    resume: Option<Checkpoint>,
+}
+
+impl<'a> Annealer<'a> {
+    pub fn new(config: Config) -> Self {
+        Annealer {
+            config,
+            moves: vec![],
+            base_weights: vec![],
+            weights: vec![],
+            segment: vec![],
+            stats: Stats::default(),
+            checkpoint: None,
+            output: None,
+            resume: None,
+        }
+    }
+
+    // intervalごとと終了時に、最良解と乱数の状態をpathに書き出す
+    pub fn with_checkpoint(mut self, path: &str, interval: Duration) -> Self {
+        self.checkpoint = Some((path.to_owned(), interval));
+        self
+    }
+
+    // チェックポイントと同じタイミングで、最良解が良くなっていればoutputにも書き出す
+    pub fn with_output(mut self, output: &'a BestOutput<'a>) -> Self {
+        self.output = Some(output);
+        self
+    }
+
+    // checkpointの回数、乱数、温度から続ける。stateはcheckpoint.solutionから作っておく
+    pub fn resume_from(mut self, checkpoint: Checkpoint) -> Self {
+        self.resume = Some(checkpoint);
+        self
+    }
+
+    // weightに比例する確率で選ばれる
+    pub fn with_move(mut self, m: impl Move + 'a, weight: f64) -> Self {
+        self.moves.push(Box::new(m));
+        self.base_weights.push(weight);
+        self
+    }
+
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    fn save(&self, solution: &Solution, score: Score, iteration: u64, temp: f64, rng: &Pcg64Mcg) {
+        if let Some((path, _)) = &self.checkpoint {
+            let checkpoint = Checkpoint {
+                solution: solution.clone(),
+                score,
+                iteration,
+                temperature: temp,
+                rng: rng.clone(),
+            };
+            if let Err(e) = checkpoint.save(path) {
+                eprintln!("failed to write checkpoint: {:#}", e);
+            }
+        }
+        if let Some(output) = self.output {
+            if let Err(e) = output.offer(solution) {
+                eprintln!("failed to write output: {:#}", e);
+            }
+        }
+    }
+
+    // 各近傍が選ばれる確率
+    fn shares(&self) -> Vec<f64> {
+        let n = self.weights.len() as f64;
+        let total: f64 = self.weights.iter().sum();
+        let min_share = match self.config.selection {
+            Selection::Fixed => 0.0,
+            Selection::Adaptive { min_share, .. } => min_share,
+        };
+        if total <= 0.0 {
+            return vec![1.0 / n; self.weights.len()];
+        }
+        self.weights
+            .iter()
+            .map(|w| (1.0 - min_share) * w / total + min_share / n)
+            .collect()
+    }
+
+    fn choose_move(&self, rng: &mut Pcg64Mcg) -> usize {
+        let shares = self.shares();
+        let mut r = rng.gen::<f64>();
+        for (i, &s) in shares.iter().enumerate() {
+            if r < s {
+                return i;
+            }
+            r -= s;
+        }
+        shares.len() - 1
+    }
+
+    fn reward(&mut self, i: usize, reward: f64) {
+        self.segment[i].0 += 1;
+        self.segment[i].1 += reward;
+    }
+
+    // segmentの成績を重みに反映する
+    fn update_weights(&mut self, iteration: u64) {
+        let Selection::Adaptive {
+            segment, reaction, ..
+        } = self.config.selection
+        else {
+            return;
+        };
+        if iteration % segment != 0 {
+            return;
+        }
+        for (w, (uses, reward)) in self.weights.iter_mut().zip(self.segment.iter_mut()) {
+            if *uses > 0 {
+                *w = (1.0 - reaction) * *w + reaction * *reward / *uses as f64;
+            }
+            *uses = 0;
+            *reward = 0.0;
+        }
+    }
+
+    // budgetを使い切るまで回し、最良のスコアと解を返す。stateは最後に受理した状態になる
+    pub fn run<S: State>(
+        &mut self,
+        state: &mut S,
+        rng: &mut Pcg64Mcg,
+        budget: &Budget,
+    ) -> (Score, Solution) {
+        assert!(!self.moves.is_empty(), "no moves");
+        let start = Instant::now();
+        let mut last_progress: Option<Instant> = None;
+        let mut current = state.score();
+        let mut best_score = current;
+        let mut best_solution = state.solution();
+        let total: f64 = self.base_weights.iter().sum();
+        self.weights = self.base_weights.iter().map(|w| w / total).collect();
+        self.segment = vec![(0, 0.0); self.moves.len()];
+        let mut late = match self.config.acceptance {
+            Acceptance::LateAcceptance(length) => vec![current; length],
+            _ => vec![],
+        };
+        self.stats = Stats {
+            initial_score: current,
+            best_score,
+            moves: self
+                .moves
+                .iter()
+                .map(|m| MoveStats {
+                    name: m.name().to_owned(),
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        };
+
+        let mut iteration = 0;
+        // 再開したときは保存した温度より熱くしない
+        let mut max_temp = f64::INFINITY;
+        if let Some(checkpoint) = self.resume.take() {
+            iteration = checkpoint.iteration;
+            max_temp = checkpoint.temperature;
+            *rng = checkpoint.rng;
+        }
+        let first_iteration = iteration;
+        let mut temp = self
+            .config
+            .schedule
+            .temperature(iteration, budget.progress(iteration))
+            .min(max_temp);
+        let mut last_save = Instant::now();
+        while !budget.is_exhausted(iteration) {
+            if let Some((_, interval)) = &self.checkpoint {
+                if last_save.elapsed() >= *interval {
+                    self.save(&best_solution, best_score, iteration, temp, rng);
+                    last_save = Instant::now();
+                }
+            }
+            let progress = budget.progress(iteration);
+            temp = self
+                .config
+                .schedule
+                .temperature(iteration, progress)
+                .min(max_temp);
+            let late_slot = if late.is_empty() {
+                None
+            } else {
+                Some(iteration as usize % late.len())
+            };
+            iteration += 1;
+
+            self.update_weights(iteration);
+            let i = self.choose_move(rng);
+            let stats = &mut self.stats.moves[i];
+            stats.tried += 1;
+            let applied = match self.moves[i].propose(&*state, temp, rng) {
+                Some(delta) => state.apply(&delta),
+                None => false,
+            };
+            if !applied {
+                stats.failed += 1;
+                self.reward(i, 0.0);
+                continue;
+            }
+
+            let new_score = state.score();
+            let late_score = late_slot.map(|slot| late[slot]);
+            if self
+                .config
+                .acceptance
+                .accepts(new_score, current, late_score, temp, rng)
+            {
+                state.commit();
+                stats.accepted += 1;
+                let mut reward = REWARD_ACCEPTED;
+                if new_score > current {
+                    stats.improved += 1;
+                    stats.gain += new_score - current;
+                    reward = REWARD_IMPROVED;
+                }
+                current = new_score;
+                if new_score > best_score {
+                    best_score = new_score;
+                    best_solution = state.solution();
+                    stats.best_updates += 1;
+                    reward = REWARD_BEST;
+                    if last_progress.map_or(true, |t| t.elapsed() >= PROGRESS_INTERVAL) {
+                        eprintln!(
+                            "iteration {}: {} best score: {}",
+                            iteration, stats.name, best_score
+                        );
+                        last_progress = Some(Instant::now());
+                    }
+                }
+                self.reward(i, reward);
+            } else {
+                state.rollback();
+                self.reward(i, 0.0);
+            }
+            if let Some(slot) = late_slot {
+                late[slot] = current;
+            }
+        }
+
+        if self.checkpoint.is_some() || self.output.is_some() {
+            self.save(&best_solution, best_score, iteration, temp, rng);
+        }
+        self.stats.iterations = iteration - first_iteration;
+        self.stats.elapsed = start.elapsed();
+        self.stats.best_score = best_score;
+        let shares = self.shares();
+        for (stats, share) in self.stats.moves.iter_mut().zip(shares) {
+            stats.share = share;
+        }
+        (best_score, best_solution)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cross_check::random_case;
+    use crate::incremental::IncrementalScorer;
+    use crate::problem::ScoringRules;
+
+    // ランダムに1人動かすか、volumeを変える
+    struct Shift;
+
+    impl Move for Shift {
+        fn name(&self) -> &str {
+            "shift"
+        }
+
+        fn propose(&mut self, state: &dyn State, _temp: f64, rng: &mut Pcg64Mcg) -> Option<Delta> {
+            let i = rng.gen_range(0..state.placements().len());
+            if rng.gen_range(0..4) == 0 {
+                return Some(Delta::volume(state, i, rng.gen_range(0.0..=MAX_VOLUME)));
+            }
+            let d = Point::new(rng.gen_range(-5.0..5.0), rng.gen_range(-5.0..5.0));
+            Some(Delta::relocate(state, &[(i, state.placements()[i] + d)]))
+        }
+    }
+
+    fn config(acceptance: Acceptance) -> Config {
+        Config {
+            acceptance,
+            schedule: Schedule::Exponential {
+                start: 1e6,
+                end: 1.0,
+            },
+            selection: Selection::ADAPTIVE,
+        }
+    }
+
+    // 何も提案しない近傍
+    struct Useless;
+
+    impl Move for Useless {
+        fn name(&self) -> &str {
+            "useless"
+        }
+
+        fn propose(&mut self, _: &dyn State, _: f64, _: &mut Pcg64Mcg) -> Option<Delta> {
+            None
+        }
+    }
+
+    #[test]
+    fn adaptive_selection_favors_improving_moves() {
+        let mut rng = Pcg64Mcg::new(8);
+        let case = random_case(&mut rng, ScoringRules::FULL);
+        let mut state = SolutionState::new(&case.input, case.solution.clone()).unwrap();
+        let mut annealer = Annealer::new(config(Acceptance::Metropolis))
+            .with_move(Useless, 10.0)
+            .with_move(Shift, 1.0);
+        annealer.run(&mut state, &mut rng, &Budget::iterations(5000));
+        let stats = annealer.stats();
+        // 失敗し続ける近傍はmin_shareの等分 (0.05) の近くまで下がる
+        assert!(stats.moves[0].share < 0.1, "{}", stats);
+        assert_eq!(stats.moves[0].failed, stats.moves[0].tried);
+        assert!(stats.moves[1].tried > stats.moves[0].tried, "{}", stats);
+
+        let mut fixed = Annealer::new(Config {
+            selection: Selection::Fixed,
+            ..config(Acceptance::Metropolis)
+        })
+        .with_move(Useless, 3.0)
+        .with_move(Shift, 1.0);
+        fixed.run(&mut state, &mut rng, &Budget::iterations(100));
+        assert_eq!(fixed.stats().moves[0].share, 0.75);
+    }
+
+    #[test]
+    fn checkpoint_and_resume() {
+        let mut rng = Pcg64Mcg::new(3);
+        let case = random_case(&mut rng, ScoringRules::FULL);
+        let input = &case.input;
+        let dir = std::env::temp_dir().join(format!("annealing-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let checkpoint_path = dir.join("checkpoint.json").to_str().unwrap().to_owned();
+        let output_path = dir.join("output.json").to_str().unwrap().to_owned();
+        let output = BestOutput::new(input, &output_path, &case.solution).unwrap();
+
+        let mut state = SolutionState::new(input, case.solution.clone()).unwrap();
+        let mut annealer = Annealer::new(config(Acceptance::Metropolis))
+            .with_move(Shift, 1.0)
+            .with_checkpoint(&checkpoint_path, Duration::ZERO)
+            .with_output(&output);
+        let (best_score, _) = annealer.run(&mut state, &mut rng, &Budget::iterations(100));
+        let checkpoint = Checkpoint::load(&checkpoint_path).unwrap();
+        assert_eq!(checkpoint.score, best_score);
+        assert_eq!(checkpoint.iteration, 100);
+        assert_eq!(checkpoint.rng, rng);
+        assert_eq!(output.best_score(), best_score);
+
+        // 回数の上限は通算で数える
+        let mut state = SolutionState::new(input, checkpoint.solution.clone()).unwrap();
+        let mut resumed = Annealer::new(config(Acceptance::Metropolis))
+            .with_move(Shift, 1.0)
+            .with_checkpoint(&checkpoint_path, Duration::ZERO)
+            .with_output(&output)
+            .resume_from(checkpoint);
+        let (resumed_score, _) =
+            resumed.run(&mut state, &mut Pcg64Mcg::new(0), &Budget::iterations(150));
+        assert_eq!(resumed.stats().iterations, 50);
+        assert!(resumed_score >= best_score);
+        assert_eq!(Checkpoint::load(&checkpoint_path).unwrap().iteration, 150);
+        let written: Solution =
+            serde_json::from_str(&std::fs::read_to_string(&output_path).unwrap()).unwrap();
+        assert_eq!(input.score_fast(&written).unwrap(), resumed_score);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn cancelled_run_returns_initial_solution() {
+        let mut rng = Pcg64Mcg::new(2);
+        let case = random_case(&mut rng, ScoringRules::FULL);
+        let mut state = SolutionState::new(&case.input, case.solution.clone()).unwrap();
+        let initial = state.score();
+        let budget = Budget::unlimited();
+        budget.cancel();
+        let mut annealer = Annealer::new(config(Acceptance::Greedy)).with_move(Shift, 1.0);
+        let (best_score, best_solution) = annealer.run(&mut state, &mut rng, &budget);
+        assert_eq!(best_score, initial);
+        assert_eq!(best_solution.placements, case.solution.placements);
+        assert_eq!(best_solution.volumes, case.solution.volumes);
+        assert_eq!(annealer.stats().iterations, 0);
+    }
+
+    fn check_run<S: State>(state: &mut S, acceptance: Acceptance, rng: &mut Pcg64Mcg) {
+        let input = state.input().clone();
+        let initial = state.score();
+        let mut annealer = Annealer::new(config(acceptance)).with_move(Shift, 1.0);
+        let (best_score, best_solution) = annealer.run(state, rng, &Budget::iterations(100));
+        assert!(best_score >= initial);
+        assert!(input.check_solution(&best_solution).is_ok());
+        assert_eq!(input.score_fast(&best_solution).unwrap(), best_score);
+        assert_eq!(input.score_fast(&state.solution()).unwrap(), state.score());
+        if acceptance == Acceptance::Greedy {
+            assert_eq!(state.score(), best_score);
+        }
+        let stats = annealer.stats();
+        assert_eq!(stats.iterations, 100);
+        assert_eq!(stats.moves[0].tried, 100);
+        assert_eq!(stats.best_score, best_score);
+    }
+
+    #[test]
+    fn runs_keep_scores_exact() {
+        let mut rng = Pcg64Mcg::new(5);
+        for acceptance in [
+            Acceptance::Greedy,
+            Acceptance::Metropolis,
+            Acceptance::LateAcceptance(10),
+            Acceptance::Threshold,
+        ] {
+            for _ in 0..5 {
+                let case = random_case(&mut rng, ScoringRules::FULL);
+                let mut state = SolutionState::new(&case.input, case.solution.clone()).unwrap();
+                check_run(&mut state, acceptance, &mut rng);
+                let mut scorer = IncrementalScorer::new(&case.input, &case.solution);
+                check_run(&mut scorer, acceptance, &mut rng);
+            }
+        }
+    }
+
+    #[test]
+    fn delta_apply_and_revert() {
+        let mut rng = Pcg64Mcg::new(2);
+        let case = random_case(&mut rng, ScoringRules::FULL);
+        let state = SolutionState::new(&case.input, case.solution.clone()).unwrap();
+        let mut delta = Delta::swap(&state, 0, 1);
+        delta.set_volume(&state, 1, 0.0);
+        delta.set_volume(&state, 2, MAX_VOLUME);
+        assert_eq!(delta.touched().collect::<Vec<_>>(), vec![0, 1, 2]);
+
+        let mut solution = case.solution.clone();
+        let inverse = delta.apply_to(&mut solution);
+        assert_eq!(solution.placements[0], case.solution.placements[1]);
+        assert_eq!(solution.volume(1), 0.0);
+        assert_eq!(solution.volume(2), MAX_VOLUME);
+        inverse.apply_to(&mut solution);
+        assert_eq!(solution.placements, case.solution.placements);
+        assert_eq!(solution.volumes(), case.solution.volumes());
+    }
+
+    #[test]
+    fn parse_acceptance() {
+        assert_eq!("greedy".parse::<Acceptance>().unwrap(), Acceptance::Greedy);
+        assert_eq!(
+            "late:50".parse::<Acceptance>().unwrap(),
+            Acceptance::LateAcceptance(50)
+        );
+        assert!("late:0".parse::<Acceptance>().is_err());
+        assert!("hot".parse::<Acceptance>().is_err());
+    }
+}
@@ -0,0 +1,269 @@
+// 手元に置く解の保管庫。問題ごとのディレクトリに、解をスコアや作り方と一緒に1ファイルずつ残す
+// <root>/<problem_id>/<unix_ms>-<score>-<solver>.json
+use crate::checkpoint::write_atomic;
+use crate::problem::{Input, Score, Solution};
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Entry {
+    pub problem_id: u32,
+    // 入れるときにscore_fastで計算した正確なスコア
+    pub score: Score,
+    pub solver: String,
+    pub seed: u128,
+    pub git_revision: String,
+    // unix time (ミリ秒)
+    pub timestamp: u64,
+    pub solution: Solution,
+}
+
+pub struct Archive {
+    root: PathBuf,
+}
+
+// 問題ごとのロック。比べてから書くまでの間に、他のプロセスやスレッドが書かないようにする
+// flockなので、閉じるかプロセスが終われば外れる
+struct ProblemLock {
+    _file: File,
+}
+
+impl ProblemLock {
+    fn acquire(dir: &Path) -> Result<Self> {
+        std::fs::create_dir_all(dir)?;
+        let file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .open(dir.join(".lock"))?;
+        if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX) } != 0 {
+            return Err(std::io::Error::last_os_error())
+                .with_context(|| format!("failed to lock {}", dir.display()));
+        }
+        Ok(ProblemLock { _file: file })
+    }
+}
+
+// 実行しているディレクトリのgitのリビジョン。わからなければ"unknown"
+pub fn git_revision() -> String {
+    std::process::Command::new("git")
+        .args(["describe", "--always", "--dirty"])
+        .output()
+        .ok()
+        .filter(|out| out.status.success())
+        .and_then(|out| String::from_utf8(out.stdout).ok())
+        .map(|s| s.trim().to_owned())
+        .unwrap_or_else(|| "unknown".to_owned())
+}
+
+impl Archive {
+    pub fn open(root: &str) -> Result<Self> {
+        std::fs::create_dir_all(root).with_context(|| format!("failed to create {root}"))?;
+        Ok(Archive { root: root.into() })
+    }
+
+    // 古い順
+    pub fn history(&self, problem_id: u32) -> Result<Vec<Entry>> {
+        let dir = self.dir(problem_id);
+        if !dir.exists() {
+            return Ok(vec![]);
+        }
+        let mut entries = vec![];
+        for file in std::fs::read_dir(&dir)? {
+            let path = file?.path();
+            if path.extension().map_or(true, |ext| ext != "json") {
+                continue;
+            }
+            let s = std::fs::read_to_string(&path)?;
+            let entry: Entry = serde_json::from_str(&s)
+                .with_context(|| format!("failed to parse {}", path.display()))?;
+            entries.push(entry);
+        }
+        entries.sort_by_key(|e| e.timestamp);
+        Ok(entries)
+    }
+
+    // スコアが同じなら先に入ったもの
+    pub fn best(&self, problem_id: u32) -> Result<Option<Entry>> {
+        let history = self.history(problem_id)?;
+        Ok(history.into_iter().rev().max_by_key(|e| e.score))
+    }
+
+    fn dir(&self, problem_id: u32) -> PathBuf {
+        self.root.join(problem_id.to_string())
+    }
+
+    // 正しい解なら必ず残す
+    pub fn insert(
+        &self,
+        problem_id: u32,
+        input: &Input,
+        solution: &Solution,
+        solver: &str,
+        seed: u128,
+    ) -> Result<Entry> {
+        if let Err(errors) = input.check_solution(solution) {
+            bail!("invalid solution: {}", errors);
+        }
+        let score = input.score_fast(solution)?;
+        let _lock = ProblemLock::acquire(&self.dir(problem_id))?;
+        self.write_entry(problem_id, score, solution, solver, seed)
+    }
+
+    // ロックを取ってから呼ぶ
+    fn write_entry(
+        &self,
+        problem_id: u32,
+        score: Score,
+        solution: &Solution,
+        solver: &str,
+        seed: u128,
+    ) -> Result<Entry> {
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis() as u64;
+        let entry = Entry {
+            problem_id,
+            score,
+            solver: solver.to_owned(),
+            seed,
+            git_revision: git_revision(),
+            timestamp,
+            solution: solution.clone(),
+        };
+        let dir = self.dir(problem_id);
+        // ファイル名には記号を入れない
+        let name: String = solver
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        let mut path = dir.join(format!("{}-{}-{}.json", timestamp, entry.score, name));
+        let mut n = 1;
+        while path.exists() {
+            path = dir.join(format!("{}-{}-{}-{}.json", timestamp, entry.score, name, n));
+            n += 1;
+        }
+        write_atomic(&path, &entry)?;
+        Ok(entry)
+    }
+
+    // 今のベストより良いときだけ残す。残したらSome
+    // 比べて書くまでロックを持つので、同時に入れても悪いほうが後に残ることはない
+    pub fn insert_if_better(
+        &self,
+        problem_id: u32,
+        input: &Input,
+        solution: &Solution,
+        solver: &str,
+        seed: u128,
+    ) -> Result<Option<Entry>> {
+        if let Err(errors) = input.check_solution(solution) {
+            bail!("invalid solution: {}", errors);
+        }
+        let score = input.score_fast(solution)?;
+        let _lock = ProblemLock::acquire(&self.dir(problem_id))?;
+        if let Some(best) = self.best(problem_id)? {
+            if score <= best.score {
+                return Ok(None);
+            }
+        }
+        Ok(Some(
+            self.write_entry(problem_id, score, solution, solver, seed)?,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cross_check::random_case;
+    use crate::problem::ScoringRules;
+    use rand_pcg::Pcg64Mcg;
+
+    #[test]
+    fn keep_if_better() {
+        let mut rng = Pcg64Mcg::new(6);
+        let case = random_case(&mut rng, ScoringRules::FULL);
+        let input = &case.input;
+        let dir = std::env::temp_dir().join(format!("archive-test-{}", std::process::id()));
+        let archive = Archive::open(dir.to_str().unwrap()).unwrap();
+        assert!(archive.best(1).unwrap().is_none());
+
+        let score = input.score_fast(&case.solution).unwrap();
+        let entry = archive
+            .insert_if_better(1, input, &case.solution, "test", 3)
+            .unwrap()
+            .unwrap();
+        assert_eq!(entry.score, score);
+        assert_eq!(entry.seed, 3);
+        // 同じスコアでは入れない
+        assert!(archive
+            .insert_if_better(1, input, &case.solution, "test", 4)
+            .unwrap()
+            .is_none());
+
+        let mut worse = case.solution.clone();
+        worse.volumes = Some(vec![0.0; input.musicians.len()]);
+        let worse_score = input.score_fast(&worse).unwrap();
+        archive.insert(1, input, &worse, "zero volume", 5).unwrap();
+        assert_eq!(archive.history(1).unwrap().len(), 2);
+        let best = archive.best(1).unwrap().unwrap();
+        assert_eq!(best.score, score.max(worse_score));
+        assert!(archive.history(2).unwrap().is_empty());
+
+        if input.musicians.len() >= 2 {
+            let mut invalid = case.solution.clone();
+            invalid.placements[0] = invalid.placements[1];
+            assert!(archive.insert(1, input, &invalid, "test", 0).is_err());
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn concurrent_inserts_keep_only_better() {
+        let mut rng = Pcg64Mcg::new(7);
+        let case = random_case(&mut rng, ScoringRules::FULL);
+        let input = &case.input;
+        let dir = std::env::temp_dir().join(format!("archive-race-{}", std::process::id()));
+        let archive = Archive::open(dir.to_str().unwrap()).unwrap();
+        // volumeだけ変えた、スコアの違う解を同時に入れる
+        let solutions: Vec<_> = (0..8)
+            .map(|k| {
+                let mut solution = case.solution.clone();
+                solution.volumes = Some(vec![1.0 + k as f64; input.musicians.len()]);
+                solution
+            })
+            .collect();
+        for problem_id in 0..5 {
+            let barrier = std::sync::Barrier::new(solutions.len());
+            let inserted = std::thread::scope(|s| {
+                let handles: Vec<_> = solutions
+                    .iter()
+                    .rev()
+                    .map(|solution| {
+                        let (archive, barrier) = (&archive, &barrier);
+                        s.spawn(move || {
+                            barrier.wait();
+                            archive
+                                .insert_if_better(problem_id, input, solution, "test", 0)
+                                .unwrap()
+                        })
+                    })
+                    .collect();
+                handles
+                    .into_iter()
+                    .filter_map(|h| h.join().unwrap())
+                    .count()
+            });
+            // 入った順に、どれもそれまでのベストより良い
+            let mut history = archive.history(problem_id).unwrap();
+            assert_eq!(history.len(), inserted);
+            history.sort_by_key(|e| (e.timestamp, e.score));
+            for w in history.windows(2) {
+                assert!(w[0].score < w[1].score);
+            }
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
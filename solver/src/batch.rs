@@ -0,0 +1,265 @@
+// 複数の問題をまとめて解いた結果の集計。サーバーがなくても手元のベスト解と比べられる
+use crate::problem::{Input, Score};
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::fmt::Write;
+use std::path::Path;
+
+// "1-10,42" のような指定を問題番号の列にする
+pub fn parse_ids(s: &str) -> Result<Vec<u32>> {
+    let mut ids = vec![];
+    for part in s.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        match part.split_once('-') {
+            Some((from, to)) => {
+                let from: u32 = from.trim().parse().context("bad problem id")?;
+                let to: u32 = to.trim().parse().context("bad problem id")?;
+                ids.extend(from..=to);
+            }
+            None => ids.push(part.parse().context("bad problem id")?),
+        }
+    }
+    Ok(ids)
+}
+
+// ディレクトリにあるproblem-<id>.jsonの番号を小さい順に
+pub fn problem_ids(dir: &str) -> Result<Vec<u32>> {
+    let mut ids = vec![];
+    for entry in std::fs::read_dir(dir).with_context(|| format!("failed to read {dir}"))? {
+        let name = entry?.file_name();
+        let Some(name) = name.to_str() else { continue };
+        let id = name
+            .strip_prefix("problem-")
+            .and_then(|s| s.strip_suffix(".json"))
+            .and_then(|s| s.parse().ok());
+        if let Some(id) = id {
+            ids.push(id);
+        }
+    }
+    ids.sort();
+    Ok(ids)
+}
+
+// 手元のベスト解のスコア。ないか正しくなければNone
+pub fn best_score(input: &Input, path: impl AsRef<Path>) -> Option<Score> {
+    let s = std::fs::read_to_string(path).ok()?;
+    let solution = serde_json::from_str(&s).ok()?;
+    input.check_solution(&solution).ok()?;
+    input.score_fast(&solution).ok()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    // ベストより良い、またはベストがない
+    NewBest,
+    Same,
+    Worse,
+    Error,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProblemResult {
+    pub problem_id: u32,
+    pub score: Option<Score>,
+    pub best: Option<Score>,
+    pub status: Status,
+    // Input::upper_bound
+    pub bound: Option<Score>,
+    pub elapsed_sec: f64,
+    pub error: Option<String>,
+}
+
+impl ProblemResult {
+    pub fn new(
+        problem_id: u32,
+        result: Result<Score>,
+        best: Option<Score>,
+        elapsed_sec: f64,
+    ) -> Self {
+        let (score, status, error) = match result {
+            Ok(score) => {
+                let status = match best {
+                    Some(best) if score < best => Status::Worse,
+                    Some(best) if score == best => Status::Same,
+                    _ => Status::NewBest,
+                };
+                (Some(score), status, None)
+            }
+            Err(e) => (None, Status::Error, Some(format!("{:#}", e))),
+        };
+        ProblemResult {
+            problem_id,
+            score,
+            best,
+            status,
+            bound: None,
+            elapsed_sec,
+            error,
+        }
+    }
+
+    pub fn with_bound(mut self, bound: Score) -> Self {
+        self.bound = Some(bound);
+        self
+    }
+
+    // ベストの読み書きに失敗した問題はスコアを残したままエラーにする
+    pub fn with_error(mut self, e: anyhow::Error) -> Self {
+        self.status = Status::Error;
+        self.error = Some(format!("{:#}", e));
+        self
+    }
+
+    // 保管しているベスト (今回のほうが良ければ今回の解) と上界との差
+    pub fn gap(&self) -> Option<Score> {
+        let best = match (self.score, self.best) {
+            (Some(score), Some(best)) => score.max(best),
+            (score, best) => score.or(best)?,
+        };
+        Some(self.bound? - best)
+    }
+
+    pub fn diff(&self) -> Option<Score> {
+        Some(self.score? - self.best?)
+    }
+}
+
+fn or_empty(score: Option<Score>) -> String {
+    score.map_or(String::new(), |s| s.to_string())
+}
+
+pub fn to_csv(results: &[ProblemResult]) -> String {
+    let mut s = String::from("problem_id,score,best,diff,bound,gap,status,elapsed_sec,error\n");
+    for r in results {
+        let status = serde_json::to_value(r.status).unwrap();
+        // エラー文にカンマや引用符があっても崩れないようにする
+        let error = r
+            .error
+            .as_ref()
+            .map_or(String::new(), |e| format!("\"{}\"", e.replace('"', "\"\"")));
+        writeln!(
+            s,
+            "{},{},{},{},{},{},{},{:.3},{}",
+            r.problem_id,
+            or_empty(r.score),
+            or_empty(r.best),
+            or_empty(r.diff()),
+            or_empty(r.bound),
+            or_empty(r.gap()),
+            status.as_str().unwrap(),
+            r.elapsed_sec,
+            error
+        )
+        .unwrap();
+    }
+    s
+}
+
+// summarize_result.shと同じ形の表に、合計の行を足したもの
+pub fn to_markdown(results: &[ProblemResult]) -> String {
+    let mut s = String::from("|problem_id|score|best|diff|bound|gap|comment|time|\n");
+    s.push_str("|----------|----:|---:|---:|----:|--:|-------|---:|\n");
+    for r in results {
+        let comment = match (r.status, &r.error) {
+            (Status::NewBest, _) => "new best!".to_owned(),
+            (Status::Error, Some(e)) => e.replace('|', "\\|").replace('\n', " "),
+            _ => String::new(),
+        };
+        writeln!(
+            s,
+            "|{}|{}|{}|{}|{}|{}|{}|{:.1}s|",
+            r.problem_id,
+            or_empty(r.score),
+            or_empty(r.best),
+            r.diff()
+                .map_or(String::new(), |d| format!("{:+.3e}", d as f64)),
+            r.bound
+                .map_or(String::new(), |b| format!("{:.3e}", b as f64)),
+            // 上界に対する割合
+            match (r.gap(), r.bound) {
+                (Some(gap), Some(bound)) if bound > 0 =>
+                    format!("{:.1}%", gap as f64 / bound as f64 * 100.0),
+                _ => String::new(),
+            },
+            comment,
+            r.elapsed_sec
+        )
+        .unwrap();
+    }
+    let count = |status| results.iter().filter(|r| r.status == status).count();
+    let total: Score = results.iter().filter_map(|r| r.score).sum();
+    writeln!(
+        s,
+        "\ntotal: {}, new best: {}, same: {}, worse: {}, error: {}",
+        total,
+        count(Status::NewBest),
+        count(Status::Same),
+        count(Status::Worse),
+        count(Status::Error)
+    )
+    .unwrap();
+    s
+}
+
+// summary.json, summary.csv, summary.mdを書く
+pub fn write_summary(dir: &str, results: &[ProblemResult]) -> Result<()> {
+    let dir = Path::new(dir);
+    crate::checkpoint::write_atomic(dir.join("summary.json"), &results)?;
+    std::fs::write(dir.join("summary.csv"), to_csv(results))?;
+    std::fs::write(dir.join("summary.md"), to_markdown(results))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+
+    #[test]
+    fn test_parse_ids() {
+        assert_eq!(parse_ids("1-3, 42").unwrap(), vec![1, 2, 3, 42]);
+        assert!(parse_ids("1-x").is_err());
+    }
+
+    #[test]
+    fn summary() {
+        let results = vec![
+            ProblemResult::new(1, Ok(100), Some(90), 1.0).with_bound(200),
+            ProblemResult::new(2, Ok(50), Some(90), 2.0).with_bound(360),
+            ProblemResult::new(3, Ok(10), None, 3.0),
+            ProblemResult::new(4, Err(anyhow!("invalid, \"bad\"")), Some(5), 4.0),
+        ];
+        assert_eq!(
+            results.iter().map(|r| r.status).collect::<Vec<_>>(),
+            vec![
+                Status::NewBest,
+                Status::Worse,
+                Status::NewBest,
+                Status::Error
+            ]
+        );
+        assert_eq!(results[1].diff(), Some(-40));
+        assert_eq!(results[0].gap(), Some(100));
+        assert_eq!(results[1].gap(), Some(270));
+        assert_eq!(results[2].gap(), None);
+
+        let csv = to_csv(&results);
+        let lines: Vec<_> = csv.lines().collect();
+        assert_eq!(lines.len(), 5);
+        assert_eq!(lines[1], "1,100,90,10,200,100,new_best,1.000,");
+        assert_eq!(lines[3], "3,10,,,,,new_best,3.000,");
+        assert_eq!(lines[4], "4,,5,,,,error,4.000,\"invalid, \"\"bad\"\"\"");
+
+        let md = to_markdown(&results);
+        assert!(
+            md.contains("|2|50|90|-4.000e1|3.600e2|75.0%||2.0s|"),
+            "{md}"
+        );
+        assert!(md.contains("total: 160, new best: 2, same: 0, worse: 1, error: 1"));
+
+        let failed = ProblemResult::new(5, Ok(30), None, 1.0).with_error(anyhow!("disk full"));
+        assert_eq!(failed.status, Status::Error);
+        assert_eq!(failed.score, Some(30));
+        assert_eq!(failed.error.as_deref(), Some("disk full"));
+    }
+}
@@ -0,0 +1,146 @@
+use clap::Parser;
+use rand_pcg::Pcg64Mcg;
+use std::time::Instant;
+
+use solver::cross_check::*;
+use solver::problem::*;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Check this problem instead of random cases (requires --solution)
+    #[arg(short, long)]
+    input: Option<String>,
+
+    /// Scoring rules: "lightning", "full" or a list like "pillars,volumes" (default: by problem id, full for random cases)
+    #[arg(long)]
+    rules: Option<ScoringRules>,
+
+    /// Solution JSON path for --input
+    #[arg(short, long)]
+    solution: Option<String>,
+
+    /// Re-run a reproducer JSON written by a previous run
+    #[arg(long)]
+    replay: Option<String>,
+
+    /// Number of random cases
+    #[arg(long, default_value_t = 10000)]
+    cases: usize,
+
+    /// Where to write the shrunk reproducer of the first mismatch
+    #[arg(short, long, default_value = "cross_check_repro.json")]
+    output: String,
+
+    #[arg(short, long, default_value_t = 0)]
+    rand_seed: u128,
+
+    /// Time score against score_fast on generated inputs of growing size instead of checking
+    #[arg(long)]
+    bench: bool,
+
+    /// Number of inputs per size for --bench
+    #[arg(long, default_value_t = 3)]
+    bench_cases: usize,
+}
+
+// --benchで試す (musician数, 観客数)
+const BENCH_SIZES: [(usize, usize); 4] = [(10, 100), (50, 500), (100, 1000), (300, 3000)];
+
+fn report(case: &CrossCheckCase, output: &str) -> ! {
+    let mismatch = case.mismatch().unwrap();
+    println!("mismatch: {:?}", mismatch);
+    let shrunk = shrink(case, |c| c.mismatch().is_some());
+    println!(
+        "shrunk to {} musicians, {} attendees, {} pillars: {:?}",
+        shrunk.input.musicians.len(),
+        shrunk.input.attendees.len(),
+        shrunk.input.pillars.len(),
+        shrunk.mismatch().unwrap()
+    );
+    shrunk.save(output).unwrap();
+    println!("reproducer written to {}", output);
+    std::process::exit(1);
+}
+
+// 実際の問題で両方の経路を比べ、かかった時間も表示する
+fn check_problem(args: &Args, input_path: &str) {
+    let input = solver::read_input(input_path, args.rules).unwrap();
+    let solution_path = args.solution.as_ref().expect("--solution is required");
+    let solution_str = std::fs::read_to_string(solution_path).unwrap();
+    let solution: Solution = serde_json::from_str(&solution_str).unwrap();
+    if let Err(errors) = input.check_solution(&solution) {
+        panic!("invalid solution: {}", errors);
+    }
+
+    let now = Instant::now();
+    let exact = input.score(&solution.placements).unwrap();
+    println!("score: {} ({:?})", exact, now.elapsed());
+    let now = Instant::now();
+    let fast = input.score_fast(&solution).unwrap();
+    println!("score_fast: {} ({:?})", fast, now.elapsed());
+
+    let case = CrossCheckCase::new(input, solution);
+    if case.mismatch().is_some() {
+        report(&case, &args.output);
+    }
+    println!("ok");
+}
+
+fn run_bench(args: &Args) {
+    let rules = args.rules.unwrap_or(ScoringRules::FULL);
+    let mut rng = Pcg64Mcg::new(args.rand_seed);
+    println!("musicians attendees cases       score  score_fast  speedup");
+    for (musicians, attendees) in BENCH_SIZES {
+        let cases: Vec<_> = (0..args.bench_cases)
+            .map(|_| bench_case(&mut rng, musicians, attendees, rules))
+            .collect();
+        let result = bench(&cases).unwrap();
+        println!(
+            "{:>9} {:>9} {:>5} {:>11.3?} {:>11.3?} {:>7.1}x",
+            result.musicians,
+            result.attendees,
+            result.cases,
+            result.score,
+            result.score_fast,
+            result.score.as_secs_f64() / result.score_fast.as_secs_f64()
+        );
+    }
+}
+
+fn main() {
+    let args = Args::parse();
+
+    if args.bench {
+        run_bench(&args);
+        return;
+    }
+
+    if let Some(path) = &args.replay {
+        let case = CrossCheckCase::load(path).unwrap();
+        match case.mismatch() {
+            Some(mismatch) => {
+                println!("mismatch: {:?}", mismatch);
+                std::process::exit(1);
+            }
+            None => println!("ok"),
+        }
+        return;
+    }
+
+    if let Some(input) = &args.input {
+        check_problem(&args, input);
+        return;
+    }
+
+    let rules = args.rules.unwrap_or(ScoringRules::FULL);
+    let mut rng = Pcg64Mcg::new(args.rand_seed);
+    for i in 0..args.cases {
+        let case = random_case(&mut rng, rules);
+        if case.mismatch().is_some() {
+            println!("case {}", i);
+            report(&case, &args.output);
+        }
+    }
+    println!("{} cases ok", args.cases);
+}
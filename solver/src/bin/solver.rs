@@ -0,0 +1,911 @@
+use anyhow::{anyhow, bail, Result};
+use clap::{Args, Parser, Subcommand};
+use rayon::prelude::*;
+use serde::Serialize;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use solver::annealing::{Acceptance, Selection};
+use solver::archive::Archive;
+use solver::batch;
+use solver::budget::Budget;
+use solver::checkpoint::{write_atomic, BestOutput};
+use solver::optimize::{self, Optimizer};
+use solver::pipeline::Pipeline;
+use solver::problem::*;
+use solver::server;
+use solver::solve::{self, Strategy};
+use solver::solver_util::nobishiro_estimate;
+use solver::stats::ProblemStats;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about = "ICFPC 2023 solver", long_about = None)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Make a new solution from scratch
+    Solve {
+        /// auto, random, random2, sgd, random_exact, random_exact_rect, two_stage_exact_together, garasubo or shield
+        strategy: SolveStrategy,
+        #[command(flatten)]
+        problem: ProblemArgs,
+        /// Output solution JSON path
+        #[arg(short, long)]
+        output: String,
+        #[command(flatten)]
+        run: RunArgs,
+        #[command(flatten)]
+        archive: ArchiveArgs,
+    },
+    /// Improve an existing solution; the output never scores below it
+    Optimize {
+        /// optimizer, garasubo_yakinamashi, fuji, yamanobori or volume_optimize
+        optimizer: Optimizer,
+        #[command(flatten)]
+        problem: ProblemArgs,
+        /// Solution JSON path to start from (default: the best one in --archive)
+        #[arg(short, long, required_unless_present = "archive")]
+        solution: Option<String>,
+        /// Output solution JSON path
+        #[arg(short, long)]
+        output: String,
+        #[command(flatten)]
+        run: RunArgs,
+        #[command(flatten)]
+        anneal: AnnealArgs,
+        #[command(flatten)]
+        archive: ArchiveArgs,
+    },
+    /// Run stages like "random_exact_rect -> yamanobori 60s -> optimizer 2m -> volume_optimize"
+    Pipeline {
+        /// Stages separated by "->"; a stage is a strategy or an optimizer, optionally with a limit like 60s, 2m or 5000it
+        #[arg(long, conflicts_with = "config", required_unless_present = "config")]
+        stages: Option<String>,
+        /// File with the stages, one per line or separated by "->"; "#" starts a comment
+        #[arg(long)]
+        config: Option<String>,
+        #[command(flatten)]
+        problem: ProblemArgs,
+        /// Solution JSON path to start from (needed when the first stage is an optimizer)
+        #[arg(short, long)]
+        solution: Option<String>,
+        /// Output solution JSON path
+        #[arg(short, long)]
+        output: String,
+        /// Write the score and time of each stage as JSON to this path
+        #[arg(long)]
+        report: Option<String>,
+        /// Budget for the whole pipeline; stages without their own limit use what is left
+        #[command(flatten)]
+        run: RunArgs,
+        #[command(flatten)]
+        anneal: AnnealArgs,
+        #[command(flatten)]
+        archive: ArchiveArgs,
+    },
+    /// Validate and score a solution
+    Eval {
+        #[command(flatten)]
+        problem: ProblemArgs,
+        /// Solution JSON path
+        #[arg(short, long)]
+        solution: String,
+        /// Print per-musician score tables
+        #[arg(long)]
+        explain: bool,
+        /// Number of rows in each table of --explain
+        #[arg(long, default_value_t = 10)]
+        top: usize,
+        /// Write the full score breakdown as JSON to this path
+        #[arg(long)]
+        json: Option<String>,
+    },
+    /// Print a summary of a problem
+    Inspect {
+        /// Problem JSON path
+        #[arg(short, long, required_unless_present = "problems_dir")]
+        input: Option<String>,
+        /// Inspect every problem-<id>.json in this directory instead, printing a JSON array
+        #[arg(long, conflicts_with = "input")]
+        problems_dir: Option<String>,
+        /// Only these problem ids from --problems-dir, like "1-10,42"
+        #[arg(long, requires = "problems_dir")]
+        problems: Option<String>,
+        /// Scoring rules: "lightning", "full" or a list like "pillars,volumes" (default: by problem id)
+        #[arg(long)]
+        rules: Option<ScoringRules>,
+        /// Also give a rough upper estimate of the score (slow on large problems)
+        #[arg(long)]
+        estimate: bool,
+        /// Count playing-together effects in the estimate even without the full rules
+        #[arg(long, requires = "estimate")]
+        sync_effect: bool,
+        /// Write the JSON here instead of printing it
+        #[arg(short, long)]
+        output: Option<String>,
+        /// Print the whole parsed input
+        #[arg(long, conflicts_with = "problems_dir")]
+        debug: bool,
+    },
+    /// Run a strategy over many problems in parallel and summarize the scores
+    Batch {
+        /// Strategy used for every problem (same names as `solve`, including auto)
+        strategy: SolveStrategy,
+        /// Problem ids like "1-10,42" (default: every problem in --problems-dir)
+        #[arg(long)]
+        problems: Option<String>,
+        /// Directory with problem-<id>.json
+        #[arg(long, default_value = "../problems")]
+        problems_dir: String,
+        /// Directory for answer-<id>.json and summary.{json,csv,md}
+        #[arg(long, default_value = "output")]
+        output_dir: String,
+        /// Directory with the best known answer-<id>.json to compare with
+        #[arg(long)]
+        best_dir: Option<String>,
+        /// Copy answers that beat --best-dir into it
+        #[arg(long, requires = "best_dir")]
+        update_best: bool,
+        /// Number of problems solved at the same time
+        #[arg(short, long, default_value_t = 4)]
+        jobs: usize,
+        /// Budget for each problem
+        #[command(flatten)]
+        run: RunArgs,
+        /// Compare with and keep better answers in this archive instead of --best-dir
+        #[arg(long, conflicts_with = "best_dir")]
+        archive: Option<String>,
+    },
+    /// Serve the team server endpoints offline, scoring submissions locally and keeping them in an archive
+    Serve {
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1:8080")]
+        addr: String,
+        /// Archive directory where submissions are kept
+        #[arg(long, default_value = "archive")]
+        archive: String,
+        /// Directory with problem-<id>.json
+        #[arg(long, default_value = "../problems")]
+        problems_dir: String,
+    },
+    /// Look at or add to a local archive of solutions
+    Archive {
+        /// Archive directory
+        #[arg(long)]
+        archive: String,
+        #[command(subcommand)]
+        command: ArchiveCommand,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum ArchiveCommand {
+    /// Write the best solution of a problem
+    Best {
+        #[arg(long)]
+        problem_id: u32,
+        /// Output solution JSON path (default: print only the metadata)
+        #[arg(short, long)]
+        output: Option<String>,
+    },
+    /// List every solution of a problem, oldest first
+    History {
+        #[arg(long)]
+        problem_id: u32,
+    },
+    /// Add an existing solution; it is validated and scored first
+    Import {
+        #[command(flatten)]
+        problem: ProblemArgs,
+        /// Solution JSON path
+        #[arg(short, long)]
+        solution: String,
+        /// Name of the solver that made it
+        #[arg(long, default_value = "import")]
+        solver: String,
+        #[arg(short, long, default_value_t = 0)]
+        rand_seed: u128,
+        /// Add it even if the archive already has a better one
+        #[arg(long)]
+        always: bool,
+    },
+}
+
+#[derive(Args, Debug)]
+struct ProblemArgs {
+    /// Problem JSON path
+    #[arg(short, long)]
+    input: String,
+
+    /// Scoring rules: "lightning", "full" or a list like "pillars,volumes" (default: by problem id)
+    #[arg(long)]
+    rules: Option<ScoringRules>,
+}
+
+impl ProblemArgs {
+    fn read(&self) -> Result<Input> {
+        solver::read_input(&self.input, self.rules)
+    }
+}
+
+#[derive(Args, Debug)]
+struct RunArgs {
+    #[arg(short, long, default_value_t = 0)]
+    rand_seed: u128,
+
+    /// Wall-clock limit in seconds
+    #[arg(short, long, default_value_t = 60.0)]
+    time_sec: f64,
+
+    /// Also stop when the process has used this much CPU time (summed over threads)
+    #[arg(long)]
+    cpu_sec: Option<f64>,
+
+    /// Stop after this many iterations instead of --time-sec, for reproducible results
+    #[arg(long)]
+    iterations: Option<u64>,
+}
+
+impl RunArgs {
+    // Ctrl-Cで止めても、それまでの最良解を書き出す
+    fn budget(&self) -> Budget {
+        Budget::from_args(self.time_sec, self.cpu_sec, self.iterations)
+    }
+}
+
+#[derive(Args, Debug)]
+struct AnnealArgs {
+    /// Acceptance rule: "greedy", "metropolis", "late", "late:<length>" or "threshold"
+    #[arg(long, default_value = "greedy")]
+    acceptance: Acceptance,
+
+    /// Temperature at the start, decreased exponentially to --temp-end
+    #[arg(long, default_value_t = 10000.0)]
+    temp_start: f64,
+
+    #[arg(long, default_value_t = 10.0)]
+    temp_end: f64,
+
+    /// How to pick moves: "adaptive" (favor moves that improve the score) or "fixed"
+    #[arg(long, default_value = "adaptive")]
+    selection: Selection,
+
+    /// Cooling rate of fuji
+    #[arg(short, long, default_value_t = 0.98)]
+    alpha: f64,
+
+    /// Number of attendees used by fuji and yamanobori (the ones closest to the stage)
+    #[arg(long)]
+    reduced_attendee: Option<usize>,
+
+    /// Write the best solution, RNG state and temperature here every --checkpoint-sec seconds
+    #[arg(long)]
+    checkpoint: Option<String>,
+
+    #[arg(long, default_value_t = 60)]
+    checkpoint_sec: u64,
+
+    /// Restart from the checkpoint written by a previous run with the same --checkpoint
+    #[arg(long, requires = "checkpoint")]
+    resume: bool,
+
+    /// Let optimizer also place zero-volume musicians as shields (the move of `solve shield`)
+    #[arg(long)]
+    shield: bool,
+}
+
+#[derive(Args, Debug)]
+struct ArchiveArgs {
+    /// Local archive directory; the result is added if it beats the best there
+    #[arg(long)]
+    archive: Option<String>,
+}
+
+impl ArchiveArgs {
+    fn best(&self, problem: &ProblemArgs) -> Result<Option<Solution>> {
+        let Some(root) = &self.archive else { return Ok(None) };
+        let id = solver::get_id(&problem.input)? as u32;
+        match Archive::open(root)?.best(id)? {
+            Some(entry) => Ok(Some(entry.solution)),
+            None => bail!("no solution for problem {id} in {root}"),
+        }
+    }
+
+    // 問題番号は入力のファイル名から取る
+    fn keep(
+        &self,
+        problem: &ProblemArgs,
+        input: &Input,
+        solution: &Solution,
+        solver: &str,
+        seed: u128,
+    ) -> Result<()> {
+        let Some(root) = &self.archive else { return Ok(()) };
+        let id = solver::get_id(&problem.input)? as u32;
+        let archive = Archive::open(root)?;
+        match archive.insert_if_better(id, input, solution, solver, seed)? {
+            Some(entry) => println!("archive: new best {} for problem {}", entry.score, id),
+            None => {
+                let best = archive.best(id)?.map(|e| e.score).unwrap_or_default();
+                println!("archive: kept the best {} for problem {}", best, id);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl AnnealArgs {
+    fn options(&self, seed: u128) -> optimize::Options {
+        optimize::Options {
+            seed,
+            acceptance: self.acceptance,
+            temp_start: self.temp_start,
+            temp_end: self.temp_end,
+            selection: self.selection,
+            alpha: self.alpha,
+            reduced_attendees: self.reduced_attendee,
+            checkpoint: self.checkpoint.clone(),
+            checkpoint_interval: Duration::from_secs(self.checkpoint_sec),
+            resume: self.resume,
+            shield: self.shield,
+        }
+    }
+}
+
+// 正しい解だけを書き出す
+fn write_solution(input: &Input, path: &str, solution: &Solution) -> Result<Score> {
+    if let Err(errors) = input.check_solution(solution) {
+        bail!("invalid solution: {}", errors);
+    }
+    write_atomic(path, solution)?;
+    input.score_fast(solution)
+}
+
+// solveとbatchで選べるもの。autoは問題の特徴から戦略と最適化の段を選ぶ
+#[derive(Debug, Clone, Copy)]
+enum SolveStrategy {
+    Auto,
+    Fixed(Strategy),
+}
+
+impl FromStr for SolveStrategy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "auto" => Ok(SolveStrategy::Auto),
+            _ => Ok(SolveStrategy::Fixed(s.parse()?)),
+        }
+    }
+}
+
+impl SolveStrategy {
+    // 解と、archiveに残すsolverの名前を返す
+    fn solve(
+        self,
+        input: &Input,
+        problem_id: Option<u32>,
+        run: &RunArgs,
+    ) -> Result<(Solution, String)> {
+        let strategy = match self {
+            SolveStrategy::Fixed(strategy) => strategy,
+            SolveStrategy::Auto => {
+                let stats = ProblemStats::new(problem_id, input);
+                let time_limit = match run.iterations {
+                    Some(_) => None,
+                    None => Some(Duration::from_secs_f64(run.time_sec)),
+                };
+                let (pipeline, reasons) =
+                    Pipeline::auto(input, &stats, time_limit, run.iterations)?;
+                for reason in &reasons {
+                    eprintln!("auto: {}", reason);
+                }
+                eprintln!("pipeline: {}", pipeline);
+                let options = optimize::Options {
+                    seed: run.rand_seed,
+                    ..Default::default()
+                };
+                let result = pipeline.run(input, None, &options, &run.budget(), None)?;
+                return Ok((result.solution, format!("auto: {pipeline}")));
+            }
+        };
+        let solution = solve::solve(input, strategy, run.rand_seed, &run.budget())?;
+        Ok((solution, strategy.name().to_owned()))
+    }
+}
+
+fn solve_command(
+    strategy: SolveStrategy,
+    problem: &ProblemArgs,
+    output: &str,
+    run: &RunArgs,
+    archive: &ArchiveArgs,
+) -> Result<()> {
+    let input = problem.read()?;
+    let problem_id = solver::get_id(&problem.input).ok().map(|id| id as u32);
+    let (solution, name) = strategy.solve(&input, problem_id, run)?;
+    println!("Score: {}", write_solution(&input, output, &solution)?);
+    archive.keep(problem, &input, &solution, &name, run.rand_seed)
+}
+
+fn optimize_command(
+    optimizer: Optimizer,
+    problem: &ProblemArgs,
+    solution: &Option<String>,
+    output: &str,
+    run: &RunArgs,
+    anneal: &AnnealArgs,
+    archive: &ArchiveArgs,
+) -> Result<()> {
+    let input = problem.read()?;
+    let solution = match solution {
+        Some(path) => solver::read_solution(path)?,
+        None => archive.best(problem)?.unwrap(),
+    };
+    // 最初に入力をそのまま書き出し、以後は良くなったときだけ書き換える
+    let output = BestOutput::new(&input, output, &solution)?;
+    let options = anneal.options(run.rand_seed);
+    let result = optimize::optimize(
+        &input,
+        &solution,
+        optimizer,
+        &options,
+        &run.budget(),
+        Some(&output),
+    )?;
+    output.offer(&result)?;
+    println!("Score: {}", output.best_score());
+    archive.keep(problem, &input, &result, optimizer.name(), run.rand_seed)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn pipeline_command(
+    stages: &Option<String>,
+    config: &Option<String>,
+    problem: &ProblemArgs,
+    solution: &Option<String>,
+    output: &str,
+    report: &Option<String>,
+    run: &RunArgs,
+    anneal: &AnnealArgs,
+    archive: &ArchiveArgs,
+) -> Result<()> {
+    let pipeline: Pipeline = match (stages, config) {
+        (Some(stages), _) => stages.parse()?,
+        (None, Some(config)) => Pipeline::load(config)?,
+        (None, None) => bail!("--stages or --config is required"),
+    };
+    eprintln!("pipeline: {}", pipeline);
+    let input = problem.read()?;
+    let initial = solution.as_deref().map(solver::read_solution).transpose()?;
+    // 入力の解があれば最初に書き出し、以後は良くなったときだけ書き換える
+    let best_output = match &initial {
+        Some(solution) => Some(BestOutput::new(&input, output, solution)?),
+        None => None,
+    };
+    let result = pipeline.run(
+        &input,
+        initial.as_ref(),
+        &anneal.options(run.rand_seed),
+        &run.budget(),
+        best_output.as_ref(),
+    )?;
+    let score = match &best_output {
+        Some(best_output) => {
+            best_output.offer(&result.solution)?;
+            best_output.best_score()
+        }
+        None => write_solution(&input, output, &result.solution)?,
+    };
+    if let Some(report) = report {
+        write_atomic(report, &result.stages)?;
+    }
+    println!("Score: {}", score);
+    archive.keep(
+        problem,
+        &input,
+        &result.solution,
+        &pipeline.to_string(),
+        run.rand_seed,
+    )
+}
+
+fn archive_command(root: &str, command: &ArchiveCommand) -> Result<()> {
+    let archive = Archive::open(root)?;
+    let print = |e: &solver::archive::Entry| {
+        println!(
+            "{} score: {}, solver: {}, seed: {}, git: {}",
+            e.timestamp, e.score, e.solver, e.seed, e.git_revision
+        )
+    };
+    match command {
+        ArchiveCommand::Best { problem_id, output } => {
+            let Some(entry) = archive.best(*problem_id)? else {
+                bail!("no solution for problem {problem_id}")
+            };
+            print(&entry);
+            if let Some(output) = output {
+                write_atomic(output, &entry.solution)?;
+            }
+        }
+        ArchiveCommand::History { problem_id } => {
+            for entry in archive.history(*problem_id)? {
+                print(&entry);
+            }
+        }
+        ArchiveCommand::Import {
+            problem,
+            solution,
+            solver: name,
+            rand_seed,
+            always,
+        } => {
+            let input = problem.read()?;
+            let id = solver::get_id(&problem.input)? as u32;
+            let solution = solver::read_solution(solution)?;
+            let entry = if *always {
+                Some(archive.insert(id, &input, &solution, name, *rand_seed)?)
+            } else {
+                archive.insert_if_better(id, &input, &solution, name, *rand_seed)?
+            };
+            match entry {
+                Some(entry) => print(&entry),
+                None => println!("not better than the best in the archive"),
+            }
+        }
+    }
+    Ok(())
+}
+
+fn print_musicians(title: &str, musicians: &[&MusicianScoreDetail]) {
+    println!("{}", title);
+    println!(
+        "{:>8} {:>10} {:>6} {:>10} {:>8} {:>8} {:>8} {:>16} {:>16}",
+        "musician",
+        "instrument",
+        "volume",
+        "closeness",
+        "visible",
+        "by_mus",
+        "by_pil",
+        "score",
+        "blocked_score"
+    );
+    for m in musicians {
+        println!(
+            "{:>8} {:>10} {:>6.1} {:>10.4} {:>8} {:>8} {:>8} {:>16} {:>16}",
+            m.musician_id,
+            m.instrument,
+            m.volume,
+            m.closeness,
+            m.visible_attendees,
+            m.blocked_by_musicians,
+            m.blocked_by_pillars,
+            m.score,
+            m.blocked_score
+        );
+    }
+}
+
+fn explain(breakdown: &ScoreBreakdown, top: usize) {
+    let mut musicians = breakdown.musicians.iter().collect::<Vec<_>>();
+    musicians.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
+    let n = top.min(musicians.len());
+    print_musicians(&format!("Top {} musicians:", n), &musicians[..n]);
+    println!();
+    musicians.reverse();
+    print_musicians(&format!("Bottom {} musicians:", n), &musicians[..n]);
+    println!();
+
+    // 遮られて失っているスコアが大きいmusician
+    musicians.sort_by(|a, b| b.blocked_score.partial_cmp(&a.blocked_score).unwrap());
+    print_musicians(
+        &format!("Top {} musicians by blocked score:", n),
+        &musicians[..n],
+    );
+}
+
+fn eval_command(
+    problem: &ProblemArgs,
+    solution: &str,
+    explain_scores: bool,
+    top: usize,
+    json: &Option<String>,
+) -> Result<()> {
+    let input = problem.read()?;
+    let solution = solver::read_solution(solution)?;
+
+    if let Err(errors) = input.check_solution(&solution) {
+        println!("Invalid solution: {}", errors);
+        if let Some(path) = json {
+            let json = serde_json::json!({ "errors": errors.0, "musicians": errors.musicians() });
+            std::fs::write(path, json.to_string())?;
+        }
+        return Ok(());
+    }
+
+    if !explain_scores && json.is_none() {
+        println!("Score: {}", solution.score(&input)?);
+        return Ok(());
+    }
+
+    let breakdown = input.score_breakdown(&solution)?;
+    println!("Score: {}", breakdown.score);
+    if explain_scores {
+        explain(&breakdown, top);
+    }
+    if let Some(path) = json {
+        std::fs::write(path, serde_json::to_string(&breakdown)?)?;
+    }
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct Inspected {
+    #[serde(flatten)]
+    stats: ProblemStats,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    estimate: Option<Score>,
+}
+
+// estimateがSomeのときだけ見積もる。中身はsync_effect
+fn inspect(path: &str, rules: Option<ScoringRules>, estimate: Option<bool>) -> Result<Inspected> {
+    let input = solver::read_input(path, rules)?;
+    let id = solver::get_id(path).ok().map(|id| id as u32);
+    Ok(Inspected {
+        stats: ProblemStats::new(id, &input),
+        estimate: estimate.map(|sync_effect| nobishiro_estimate(&input, sync_effect)),
+    })
+}
+
+// batchで比べる手元のベスト解
+enum BestStore {
+    None,
+    // answer-<id>.jsonが並んだディレクトリ。updateなら良くなったものを上書きする
+    Dir { dir: String, update: bool },
+    Archive(Archive),
+}
+
+impl BestStore {
+    fn score(&self, id: u32, input: &Input) -> Result<Option<Score>> {
+        Ok(match self {
+            BestStore::None => None,
+            BestStore::Dir { dir, .. } => {
+                batch::best_score(input, format!("{dir}/answer-{id}.json"))
+            }
+            BestStore::Archive(archive) => archive.best(id)?.map(|e| e.score),
+        })
+    }
+
+    fn keep(
+        &self,
+        id: u32,
+        input: &Input,
+        solution: &Solution,
+        solver: &str,
+        seed: u128,
+    ) -> Result<()> {
+        match self {
+            BestStore::None | BestStore::Dir { update: false, .. } => {}
+            BestStore::Dir { dir, update: true } => {
+                write_atomic(format!("{dir}/answer-{id}.json"), solution)?
+            }
+            BestStore::Archive(archive) => {
+                archive.insert_if_better(id, input, solution, solver, seed)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn batch_command(
+    strategy: SolveStrategy,
+    problems: &Option<String>,
+    problems_dir: &str,
+    output_dir: &str,
+    best_store: &BestStore,
+    jobs: usize,
+    run: &RunArgs,
+) -> Result<()> {
+    let ids = match problems {
+        Some(problems) => batch::parse_ids(problems)?,
+        None => batch::problem_ids(problems_dir)?,
+    };
+    std::fs::create_dir_all(output_dir)?;
+    let pool = rayon::ThreadPoolBuilder::new().num_threads(jobs).build()?;
+    let results = pool.install(|| {
+        ids.par_iter()
+            .map(|&id| {
+                let start = Instant::now();
+                let input = solver::read_input(&format!("{problems_dir}/problem-{id}.json"), None);
+                let best = match &input {
+                    Ok(input) => best_store.score(id, input),
+                    Err(_) => Ok(None),
+                };
+                // ベストが読めなくても解くだけは解いてsummaryに残す
+                let (best, best_error) = match best {
+                    Ok(best) => (best, None),
+                    Err(e) => (None, Some(e)),
+                };
+                // 問題ごとにbudgetを作る
+                let solution = input
+                    .as_ref()
+                    .map_err(|e| anyhow!("{:#}", e))
+                    .and_then(|input| {
+                        let (solution, name) = strategy.solve(input, Some(id), run)?;
+                        let score = write_solution(
+                            input,
+                            &format!("{output_dir}/answer-{id}.json"),
+                            &solution,
+                        )?;
+                        Ok((score, solution, name))
+                    });
+                let result = batch::ProblemResult::new(
+                    id,
+                    solution
+                        .as_ref()
+                        .map(|(score, _, _)| *score)
+                        .map_err(|e| anyhow!("{:#}", e)),
+                    best,
+                    start.elapsed().as_secs_f64(),
+                );
+                let bound = input.as_ref().ok().and_then(|input| input.upper_bound());
+                let mut result = match bound {
+                    Some(bound) => result.with_bound(bound),
+                    None => result,
+                };
+                if let Some(e) = best_error {
+                    result = result.with_error(e.context("failed to read the best solution"));
+                } else if let (Ok(input), Ok((_, solution, name))) = (&input, &solution) {
+                    if result.status == batch::Status::NewBest {
+                        if let Err(e) = best_store.keep(id, input, solution, name, run.rand_seed) {
+                            result = result.with_error(e.context("failed to keep the solution"));
+                        }
+                    }
+                }
+                println!(
+                    "problem {}: {}",
+                    id,
+                    match (result.score, &result.error) {
+                        (Some(score), None) => format!("Score: {}", score),
+                        (Some(score), Some(e)) => format!("Score: {} (error: {})", score, e),
+                        (None, e) => format!("error: {}", e.as_deref().unwrap_or("")),
+                    }
+                );
+                result
+            })
+            .collect::<Vec<_>>()
+    });
+    batch::write_summary(output_dir, &results)?;
+    print!("{}", batch::to_markdown(&results));
+    if results.iter().any(|r| r.status == batch::Status::Error) {
+        bail!("some problems failed");
+    }
+    Ok(())
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    match &cli.command {
+        Command::Solve {
+            strategy,
+            problem,
+            output,
+            run,
+            archive,
+        } => solve_command(*strategy, problem, output, run, archive),
+        Command::Optimize {
+            optimizer,
+            problem,
+            solution,
+            output,
+            run,
+            anneal,
+            archive,
+        } => optimize_command(*optimizer, problem, solution, output, run, anneal, archive),
+        Command::Pipeline {
+            stages,
+            config,
+            problem,
+            solution,
+            output,
+            report,
+            run,
+            anneal,
+            archive,
+        } => pipeline_command(
+            stages, config, problem, solution, output, report, run, anneal, archive,
+        ),
+        Command::Eval {
+            problem,
+            solution,
+            explain,
+            top,
+            json,
+        } => eval_command(problem, solution, *explain, *top, json),
+        Command::Inspect {
+            input,
+            problems_dir,
+            problems,
+            rules,
+            estimate,
+            sync_effect,
+            output,
+            debug,
+        } => {
+            let inputs = match (input, problems_dir) {
+                (Some(input), _) => vec![input.clone()],
+                (None, Some(dir)) => {
+                    let ids = match problems {
+                        Some(problems) => batch::parse_ids(problems)?,
+                        None => batch::problem_ids(dir)?,
+                    };
+                    ids.iter()
+                        .map(|id| format!("{dir}/problem-{id}.json"))
+                        .collect()
+                }
+                (None, None) => bail!("--input or --problems-dir is required"),
+            };
+            if *debug {
+                println!("Input: {:?}", solver::read_input(&inputs[0], *rules)?);
+                return Ok(());
+            }
+            let estimate = estimate.then_some(*sync_effect);
+            let stats = inputs
+                .par_iter()
+                .map(|path| inspect(path, *rules, estimate))
+                .collect::<Result<Vec<_>>>()?;
+            let json = match (input, &stats[..]) {
+                (Some(_), [stats]) => serde_json::to_string_pretty(stats)?,
+                _ => serde_json::to_string_pretty(&stats)?,
+            };
+            match output {
+                Some(output) => std::fs::write(output, json)?,
+                None => println!("{}", json),
+            }
+            Ok(())
+        }
+        Command::Batch {
+            strategy,
+            problems,
+            problems_dir,
+            output_dir,
+            jobs,
+            run,
+            best_dir,
+            update_best,
+            archive,
+        } => {
+            let best_store = match (best_dir, archive) {
+                (_, Some(root)) => BestStore::Archive(Archive::open(root)?),
+                (Some(dir), None) => BestStore::Dir {
+                    dir: dir.clone(),
+                    update: *update_best,
+                },
+                (None, None) => BestStore::None,
+            };
+            batch_command(
+                *strategy,
+                problems,
+                problems_dir,
+                output_dir,
+                &best_store,
+                *jobs,
+                run,
+            )
+        }
+        Command::Serve {
+            addr,
+            archive,
+            problems_dir,
+        } => server::serve(
+            addr,
+            server::Server::new(Archive::open(archive)?, problems_dir),
+        ),
+        Command::Archive { archive, command } => archive_command(archive, command),
+    }
+}
@@ -0,0 +1,262 @@
+// 1回の実行に使える時間・CPU時間・回数の上限と、外からの中断
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+// Ctrl-Cが押されたか。シグナルハンドラから書くのでstaticにする
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn on_sigint(_: libc::c_int) {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+    // 2回目のCtrl-Cではそのまま終了する
+    unsafe {
+        libc::signal(libc::SIGINT, libc::SIG_DFL);
+    }
+}
+
+// プロセス全体のCPU時間 (全スレッドの合計)
+pub fn cpu_time() -> Duration {
+    let mut ts = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    unsafe {
+        libc::clock_gettime(libc::CLOCK_PROCESS_CPUTIME_ID, &mut ts);
+    }
+    Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32)
+}
+
+// 作った時点から測り始める。cloneしたものは開始時刻と中断を共有する
+#[derive(Debug, Clone)]
+pub struct Budget {
+    start: Instant,
+    cpu_start: Duration,
+    time_limit: Option<Duration>,
+    cpu_limit: Option<Duration>,
+    max_iterations: Option<u64>,
+    // 使った回数 (is_exhaustedに渡された一番大きいiteration) を数えるカウンタと、作ったときの値
+    // 先頭がこの予算のもので、stageで作ったものは元の予算のカウンタにも足し込む
+    counters: Vec<(Arc<AtomicU64>, u64)>,
+    cancelled: Arc<AtomicBool>,
+    ctrl_c: bool,
+}
+
+impl Budget {
+    // 上限なし。cancelされるまで終わらない
+    pub fn unlimited() -> Self {
+        Budget {
+            start: Instant::now(),
+            cpu_start: cpu_time(),
+            time_limit: None,
+            cpu_limit: None,
+            max_iterations: None,
+            counters: vec![(Arc::new(AtomicU64::new(0)), 0)],
+            cancelled: Arc::new(AtomicBool::new(false)),
+            ctrl_c: false,
+        }
+    }
+
+    pub fn time(limit: Duration) -> Self {
+        Budget::unlimited().with_time_limit(limit)
+    }
+
+    // 回数だけで止めると、実行速度によらず同じ結果になる
+    pub fn iterations(max_iterations: u64) -> Self {
+        Budget::unlimited().with_max_iterations(max_iterations)
+    }
+
+    // コマンドラインの指定から作る。回数が指定されていれば経過時間は見ない
+    pub fn from_args(time_sec: f64, cpu_sec: Option<f64>, iterations: Option<u64>) -> Self {
+        let budget = match iterations {
+            Some(n) => Budget::iterations(n),
+            None => Budget::time(Duration::from_secs_f64(time_sec)),
+        };
+        let budget = match cpu_sec {
+            Some(sec) => budget.with_cpu_limit(Duration::from_secs_f64(sec)),
+            None => budget,
+        };
+        budget.with_ctrl_c()
+    }
+
+    pub fn with_time_limit(mut self, limit: Duration) -> Self {
+        self.time_limit = Some(limit);
+        self
+    }
+
+    pub fn with_cpu_limit(mut self, limit: Duration) -> Self {
+        self.cpu_limit = Some(limit);
+        self
+    }
+
+    pub fn with_max_iterations(mut self, max_iterations: u64) -> Self {
+        self.max_iterations = Some(max_iterations);
+        self
+    }
+
+    // Ctrl-Cで止まるようにする。止まった後は呼び出し側がそれまでの最良解を書き出す
+    pub fn with_ctrl_c(mut self) -> Self {
+        unsafe {
+            libc::signal(libc::SIGINT, on_sigint as libc::sighandler_t);
+        }
+        self.ctrl_c = true;
+        self
+    }
+
+    // パイプラインの1段分。今から測り始め、時間と回数は指定と残りの小さい方にする。中断は共有する
+    // 段で使った回数は元の予算でも使ったことになる
+    pub fn stage(&self, time_limit: Option<Duration>, max_iterations: Option<u64>) -> Budget {
+        let remaining =
+            |limit: Option<Duration>, used: Duration| limit.map(|l| l.saturating_sub(used));
+        let time_limit = match (time_limit, remaining(self.time_limit, self.elapsed())) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        let remaining_iterations = self
+            .max_iterations
+            .map(|m| m.saturating_sub(self.iterations_used()));
+        let max_iterations = match (max_iterations, remaining_iterations) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        let mut counters = vec![(Arc::new(AtomicU64::new(0)), 0)];
+        counters.extend(
+            self.counters
+                .iter()
+                .map(|(c, _)| (c.clone(), c.load(Ordering::SeqCst))),
+        );
+        Budget {
+            start: Instant::now(),
+            cpu_start: cpu_time(),
+            time_limit,
+            cpu_limit: remaining(self.cpu_limit, self.cpu_elapsed()),
+            max_iterations,
+            counters,
+            cancelled: self.cancelled.clone(),
+            ctrl_c: self.ctrl_c,
+        }
+    }
+
+    // これまでに使った回数。同時に回すworkerやcloneの間では一番進んだもの
+    pub fn iterations_used(&self) -> u64 {
+        self.counters[0].0.load(Ordering::SeqCst)
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    pub fn cpu_elapsed(&self) -> Duration {
+        cpu_time().saturating_sub(self.cpu_start)
+    }
+
+    // 他のスレッドから止める
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst) || (self.ctrl_c && INTERRUPTED.load(Ordering::SeqCst))
+    }
+
+    // iteration回終えたところで止めるべきか
+    pub fn is_exhausted(&self, iteration: u64) -> bool {
+        for (counter, base) in &self.counters {
+            counter.fetch_max(base + iteration, Ordering::SeqCst);
+        }
+        self.is_cancelled()
+            || self.max_iterations.map_or(false, |m| iteration >= m)
+            || self.time_limit.map_or(false, |t| self.elapsed() >= t)
+            || self.cpu_limit.map_or(false, |t| self.cpu_elapsed() >= t)
+    }
+
+    // 温度などを決めるための進み具合 (0.0..=1.0)。複数の上限があれば一番進んでいるもの
+    pub fn progress(&self, iteration: u64) -> f64 {
+        let fraction = |used: f64, limit: Option<f64>| limit.map_or(0.0, |l| used / l);
+        let by_iterations = fraction(iteration as f64, self.max_iterations.map(|m| m as f64));
+        let by_time = fraction(
+            self.elapsed().as_secs_f64(),
+            self.time_limit.map(|t| t.as_secs_f64()),
+        );
+        let by_cpu = fraction(
+            self.cpu_elapsed().as_secs_f64(),
+            self.cpu_limit.map(|t| t.as_secs_f64()),
+        );
+        by_iterations.max(by_time).max(by_cpu).min(1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn iteration_budget() {
+        let budget = Budget::iterations(10);
+        assert!(!budget.is_exhausted(9));
+        assert!(budget.is_exhausted(10));
+        assert_eq!(budget.progress(5), 0.5);
+        assert_eq!(budget.progress(20), 1.0);
+    }
+
+    #[test]
+    fn time_budgets() {
+        let budget = Budget::time(Duration::ZERO);
+        assert!(budget.is_exhausted(0));
+        assert_eq!(budget.progress(0), 1.0);
+
+        let budget = Budget::unlimited().with_cpu_limit(Duration::from_millis(20));
+        assert!(!budget.is_exhausted(0));
+        let mut x = 0u64;
+        while !budget.is_exhausted(0) {
+            x = x.wrapping_mul(31).wrapping_add(1);
+        }
+        assert!(budget.cpu_elapsed() >= Duration::from_millis(20), "{x}");
+    }
+
+    #[test]
+    fn cancel_is_shared_by_clones() {
+        let budget = Budget::unlimited();
+        assert!(!budget.is_exhausted(u64::MAX));
+        assert_eq!(budget.progress(100), 0.0);
+        let worker = budget.clone();
+        let handle = std::thread::spawn(move || {
+            let mut iteration = 0;
+            while !worker.is_exhausted(iteration) {
+                iteration += 1;
+            }
+            worker.is_cancelled()
+        });
+        budget.cancel();
+        assert!(handle.join().unwrap());
+    }
+
+    #[test]
+    fn stage_budget() {
+        let budget = Budget::time(Duration::from_secs(10));
+        let stage = budget.stage(Some(Duration::from_secs(60)), None);
+        assert!(stage.time_limit.unwrap() <= Duration::from_secs(10));
+        let stage = budget.stage(None, Some(5));
+        assert!(!stage.is_exhausted(4));
+        assert!(stage.is_exhausted(5));
+        budget.cancel();
+        assert!(stage.is_exhausted(0));
+
+        let budget = Budget::iterations(100);
+        assert_eq!(budget.stage(None, None).max_iterations, Some(100));
+        assert_eq!(budget.stage(None, Some(3)).max_iterations, Some(3));
+
+        // 各段は残りの回数だけ使える
+        let first = budget.stage(None, Some(25));
+        assert!(first.is_exhausted(25));
+        assert_eq!(budget.iterations_used(), 25);
+        let second = budget.stage(None, None);
+        assert_eq!(second.max_iterations, Some(75));
+        let inner = second.stage(None, Some(10));
+        assert!(!inner.is_exhausted(9));
+        assert_eq!(budget.iterations_used(), 34);
+        assert!(!second.is_exhausted(70));
+        assert_eq!(budget.stage(None, Some(500)).max_iterations, Some(5));
+        assert!(second.is_exhausted(75));
+        assert!(budget.stage(None, None).is_exhausted(0));
+    }
+}
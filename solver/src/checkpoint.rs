@@ -0,0 +1,147 @@
+// 長い実行を途中で止めても失わないための書き出しと再開
+use crate::problem::{Input, Score, Solution};
+use anyhow::{bail, Context, Result};
+use rand_pcg::Pcg64Mcg;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::Mutex;
+
+// 一時ファイルに書いてからrenameする。途中で止まっても壊れたファイルが残らない
+pub fn write_atomic<T: Serialize>(path: impl AsRef<Path>, value: &T) -> Result<()> {
+    let path = path.as_ref();
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    std::fs::write(&tmp, serde_json::to_string(value)?)
+        .with_context(|| format!("failed to write {}", Path::new(&tmp).display()))?;
+    std::fs::rename(&tmp, path).with_context(|| format!("failed to rename to {}", path.display()))
+}
+
+// 焼きなましを再開するのに必要なもの
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Checkpoint {
+    // それまでの最良解。再開するときはここから始める
+    pub solution: Solution,
+    pub score: Score,
+    pub iteration: u64,
+    pub temperature: f64,
+    pub rng: Pcg64Mcg,
+}
+
+impl Checkpoint {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let s = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        Ok(serde_json::from_str(&s)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        write_atomic(path, self)
+    }
+}
+
+// 出力ファイルに、常に入力以上のスコアの正しい解が入っているようにする
+// 複数のworkerから呼んでも、良くなるときしか書き換えない
+pub struct BestOutput<'a> {
+    input: &'a Input,
+    path: String,
+    best: Mutex<Score>,
+}
+
+impl<'a> BestOutput<'a> {
+    // solutionをすぐに書き出す。ただし出力ファイルにもっと良い正しい解があればそれを残す
+    pub fn new(input: &'a Input, path: &str, solution: &Solution) -> Result<Self> {
+        let output = BestOutput {
+            input,
+            path: path.to_owned(),
+            best: Mutex::new(Score::MIN),
+        };
+        if let Ok(s) = std::fs::read_to_string(path) {
+            if let Ok(existing) = serde_json::from_str::<Solution>(&s) {
+                if input.check_solution(&existing).is_ok() {
+                    *output.best.lock().unwrap() = input.score_fast(&existing)?;
+                }
+            }
+        }
+        output.offer(solution)?;
+        Ok(output)
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn best_score(&self) -> Score {
+        *self.best.lock().unwrap()
+    }
+
+    // 今までより良ければ書き出してtrueを返す。正しくない解はエラー
+    pub fn offer(&self, solution: &Solution) -> Result<bool> {
+        if let Err(errors) = self.input.check_solution(solution) {
+            bail!("invalid solution: {}", errors);
+        }
+        let score = self.input.score_fast(solution)?;
+        let mut best = self.best.lock().unwrap();
+        if score <= *best {
+            return Ok(false);
+        }
+        write_atomic(&self.path, solution)?;
+        *best = score;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cross_check::random_case;
+    use crate::problem::ScoringRules;
+
+    #[test]
+    fn output_only_improves() {
+        let mut rng = Pcg64Mcg::new(6);
+        let case = random_case(&mut rng, ScoringRules::FULL);
+        let input = &case.input;
+        let dir = std::env::temp_dir().join(format!("checkpoint-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("output.json").to_str().unwrap().to_owned();
+
+        let score = input.score_fast(&case.solution).unwrap();
+        let output = BestOutput::new(input, &path, &case.solution).unwrap();
+        assert_eq!(output.best_score(), score);
+
+        // 全員の音量を0にすると悪くなるか同じになるので書き換えない
+        let mut worse = case.solution.clone();
+        worse.volumes = Some(vec![0.0; input.musicians.len()]);
+        if input.score_fast(&worse).unwrap() < score {
+            assert!(!output.offer(&worse).unwrap());
+        }
+        if input.musicians.len() >= 2 {
+            let mut invalid = case.solution.clone();
+            invalid.placements[0] = invalid.placements[1];
+            assert!(output.offer(&invalid).is_err());
+        }
+
+        // 作り直しても、ファイルにある解の方が良ければそちらを残す
+        let reopened = BestOutput::new(input, &path, &worse).unwrap();
+        assert_eq!(reopened.best_score(), score);
+        let written: Solution =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(input.score_fast(&written).unwrap(), score);
+
+        let checkpoint = Checkpoint {
+            solution: case.solution.clone(),
+            score,
+            iteration: 12,
+            temperature: 3.5,
+            rng: rng.clone(),
+        };
+        let checkpoint_path = dir.join("checkpoint.json");
+        checkpoint.save(&checkpoint_path).unwrap();
+        let loaded = Checkpoint::load(&checkpoint_path).unwrap();
+        assert_eq!(loaded.score, score);
+        assert_eq!(loaded.iteration, 12);
+        assert_eq!(loaded.rng, rng);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
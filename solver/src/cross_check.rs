@@ -0,0 +1,537 @@
+// Input::find_blocker による線分判定 (厳密) と
+// get_non_blocked_placement_ids の角度スイープ (高速) の突き合わせ
+use crate::problem::*;
+use anyhow::{bail, Context, Result};
+use geo::{EuclideanDistance, Point};
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::time::{Duration, Instant};
+
+// 不一致の再現用JSON。rulesは問題JSONに含まれないので別に持つ
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CrossCheckCase {
+    pub rules: ScoringRules,
+    pub input: Input,
+    pub solution: Solution,
+}
+
+impl CrossCheckCase {
+    pub fn new(input: Input, solution: Solution) -> Self {
+        CrossCheckCase {
+            rules: input.rules,
+            input,
+            solution,
+        }
+    }
+
+    pub fn load(path: &str) -> Result<Self> {
+        let s = std::fs::read_to_string(path).with_context(|| format!("failed to read {path}"))?;
+        let mut case: CrossCheckCase =
+            serde_json::from_str(&s).with_context(|| format!("failed to parse {path}"))?;
+        case.input.rules = case.rules;
+        Ok(case)
+    }
+
+    pub fn save(&self, path: &str) -> Result<()> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("failed to write {path}"))
+    }
+
+    pub fn mismatch(&self) -> Option<Mismatch> {
+        find_mismatch(&self.input, &self.solution)
+    }
+}
+
+// ある参加者について2つの経路の結果が食い違ったもの
+// fastがNoneならスイープがpanicした
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Mismatch {
+    pub attendee_id: usize,
+    pub exact: Vec<usize>,
+    pub fast: Option<Vec<usize>>,
+    pub exact_score: Score,
+    pub fast_score: Option<Score>,
+}
+
+// 線分と全musician, pillarの距離を調べて見えるmusicianを返す
+pub fn visible_exact(input: &Input, attendee_id: usize, placements: &[Point]) -> Vec<usize> {
+    (0..placements.len())
+        .filter(|&m| input.find_blocker(attendee_id, m, placements).is_none())
+        .collect()
+}
+
+// score_attendee_fast と同じ手順で見えるmusicianを返す (昇順)
+pub fn visible_fast(input: &Input, attendee_id: usize, placements: &[Point]) -> Vec<usize> {
+    let pos = input.attendees[attendee_id].pos();
+    let ids = get_non_blocked_placement_ids(pos, placements);
+    let mut ids =
+        filter_placements_blocked_by_pillars(pos, placements, input.active_pillars(), &ids);
+    ids.sort();
+    ids
+}
+
+// 最初に見つかった不一致を返す。solutionは妥当であること
+pub fn find_mismatch(input: &Input, solution: &Solution) -> Option<Mismatch> {
+    if solution.placements.is_empty() {
+        return None;
+    }
+    let closeness = input.playing_together_factors(&solution.placements);
+    let grid = input.blocker_grid(&solution.placements);
+    for attendee_id in 0..input.attendees.len() {
+        let exact = visible_exact(input, attendee_id, &solution.placements);
+        let exact_score = input
+            .attendee_score_detail(attendee_id, solution, &closeness, &grid)
+            .score;
+        let fast = catch_unwind(AssertUnwindSafe(|| {
+            let ids = visible_fast(input, attendee_id, &solution.placements);
+            let score = input.score_attendee_fast(attendee_id, solution, &closeness);
+            (ids, score)
+        }));
+        match fast {
+            Ok((ids, score)) if ids == exact && score == exact_score => {}
+            Ok((ids, score)) => {
+                return Some(Mismatch {
+                    attendee_id,
+                    exact,
+                    fast: Some(ids),
+                    exact_score,
+                    fast_score: Some(score),
+                })
+            }
+            Err(_) => {
+                return Some(Mismatch {
+                    attendee_id,
+                    exact,
+                    fast: None,
+                    exact_score,
+                    fast_score: None,
+                })
+            }
+        }
+    }
+    None
+}
+
+// failsを満たしたまま attendee, musician, pillar を1つずつ取り除いて小さくする
+pub fn shrink(case: &CrossCheckCase, fails: impl Fn(&CrossCheckCase) -> bool) -> CrossCheckCase {
+    let mut case = case.clone();
+    if !fails(&case) {
+        return case;
+    }
+
+    // まず参加者1人だけで再現するものを探す
+    for i in 0..case.input.attendees.len() {
+        let mut c = case.clone();
+        c.input.attendees = vec![case.input.attendees[i].clone()];
+        if fails(&c) {
+            case = c;
+            break;
+        }
+    }
+
+    loop {
+        let mut updated = false;
+        let mut i = 0;
+        while i < case.input.attendees.len() {
+            let mut c = case.clone();
+            c.input.attendees.remove(i);
+            if fails(&c) {
+                case = c;
+                updated = true;
+            } else {
+                i += 1;
+            }
+        }
+        let mut i = 0;
+        while i < case.input.musicians.len() {
+            let mut c = case.clone();
+            c.input.musicians.remove(i);
+            c.solution.placements.remove(i);
+            if let Some(volumes) = &mut c.solution.volumes {
+                volumes.remove(i);
+            }
+            if fails(&c) {
+                case = c;
+                updated = true;
+            } else {
+                i += 1;
+            }
+        }
+        let mut i = 0;
+        while i < case.input.pillars.len() {
+            let mut c = case.clone();
+            c.input.pillars.remove(i);
+            if fails(&c) {
+                case = c;
+                updated = true;
+            } else {
+                i += 1;
+            }
+        }
+        if !updated {
+            break;
+        }
+    }
+    case
+}
+
+const INSTRUMENTS: usize = 3;
+
+// 共線・接線になりやすいケースを混ぜたランダムな入力と妥当な解
+pub fn random_case<R: Rng>(rng: &mut R, rules: ScoringRules) -> CrossCheckCase {
+    let stage_width = 10.0 * rng.gen_range(4..12) as f64;
+    let stage_height = 10.0 * rng.gen_range(4..12) as f64;
+    let margin = rng.gen_range(20..80) as f64;
+    let stage_bottom_left = Point::new(margin, margin);
+    let mut input = Input {
+        room_width: stage_width + 2.0 * margin,
+        room_height: stage_height + 2.0 * margin,
+        stage_width,
+        stage_height,
+        stage_bottom_left,
+        musicians: vec![],
+        attendees: vec![],
+        pillars: vec![],
+        rules,
+    };
+
+    // 10間隔の格子 (同じ行・列のmusicianが共線になる) かランダム配置
+    let n = rng.gen_range(2..12);
+    let mut placements = vec![];
+    if rng.gen_bool(0.5) {
+        let mut grid = vec![];
+        let mut y = margin + 10.0;
+        while y <= margin + stage_height - 10.0 {
+            let mut x = margin + 10.0;
+            while x <= margin + stage_width - 10.0 {
+                grid.push(Point::new(x, y));
+                x += 10.0;
+            }
+            y += 10.0;
+        }
+        grid.shuffle(rng);
+        placements = grid.into_iter().take(n).collect();
+    } else {
+        for _ in 0..n * 20 {
+            if placements.len() == n {
+                break;
+            }
+            let p = Point::new(
+                rng.gen_range(margin + 10.0..=margin + stage_width - 10.0),
+                rng.gen_range(margin + 10.0..=margin + stage_height - 10.0),
+            );
+            if placements
+                .iter()
+                .all(|q: &Point| q.euclidean_distance(&p) >= 10.0)
+            {
+                placements.push(p);
+            }
+        }
+    }
+    input.musicians = (0..placements.len())
+        .map(|_| rng.gen_range(0..INSTRUMENTS))
+        .collect();
+
+    let attendee_num = rng.gen_range(1..20);
+    let mut positions = vec![];
+    for _ in 0..attendee_num * 10 {
+        if positions.len() == attendee_num {
+            break;
+        }
+        let p = match rng.gen_range(0..4) {
+            0 => random_point(rng, &input),
+            1 => collinear_point(rng, &input, &placements),
+            2 => tangent_point(rng, &input, &placements),
+            _ => axis_tangent_point(rng, &input, &placements),
+        };
+        if let Some(p) = p {
+            if !in_stage_area(&input, &p) && in_room(&input, &p) {
+                positions.push(p);
+            }
+        }
+    }
+    input.attendees = positions
+        .into_iter()
+        .map(|p| Attendee {
+            x: p.x(),
+            y: p.y(),
+            tastes: (0..INSTRUMENTS)
+                .map(|_| rng.gen_range(-1000..=1000) as f64)
+                .collect(),
+        })
+        .collect();
+
+    for _ in 0..rng.gen_range(0..4) {
+        let pillar = if rng.gen_bool(0.5) {
+            tangent_pillar(rng, &input, &placements)
+        } else {
+            let p = random_point(rng, &input);
+            p.map(|center| Pillar {
+                center,
+                radius: rng.gen_range(1..10) as f64,
+            })
+        };
+        if let Some(pillar) = pillar {
+            input.pillars.push(pillar);
+        }
+    }
+
+    let volumes = (0..placements.len())
+        .map(|_| match rng.gen_range(0..4) {
+            0 => 0.0,
+            1 => DEFAULT_VOLUME,
+            2 => MAX_VOLUME,
+            _ => rng.gen_range(0.0..=MAX_VOLUME),
+        })
+        .collect();
+    CrossCheckCase::new(input, Solution::with_volumes(placements, Some(volumes)))
+}
+
+// ベンチマーク用の大きめの入力。musicianは10間隔の格子に並べ、観客はステージの外に散らす
+// Input::scoreはvolumeを見ないので、解のvolumeは付けない
+pub fn bench_case<R: Rng>(
+    rng: &mut R,
+    musicians: usize,
+    attendees: usize,
+    rules: ScoringRules,
+) -> CrossCheckCase {
+    let side = (musicians as f64).sqrt().ceil() as usize;
+    let stage_size = 10.0 * (side + 1) as f64;
+    let margin = 200.0;
+    let mut input = Input {
+        room_width: stage_size + 2.0 * margin,
+        room_height: stage_size + 2.0 * margin,
+        stage_width: stage_size,
+        stage_height: stage_size,
+        stage_bottom_left: Point::new(margin, margin),
+        musicians: (0..musicians)
+            .map(|_| rng.gen_range(0..INSTRUMENTS))
+            .collect(),
+        attendees: vec![],
+        pillars: vec![],
+        rules,
+    };
+    let placements = (0..musicians)
+        .map(|i| {
+            Point::new(
+                margin + 10.0 * (i % side + 1) as f64,
+                margin + 10.0 * (i / side + 1) as f64,
+            )
+        })
+        .collect();
+    while input.attendees.len() < attendees {
+        let p = random_point(rng, &input).unwrap();
+        if !in_stage_area(&input, &p) {
+            input.attendees.push(Attendee {
+                x: p.x(),
+                y: p.y(),
+                tastes: (0..INSTRUMENTS)
+                    .map(|_| rng.gen_range(-1000..=1000) as f64)
+                    .collect(),
+            });
+        }
+    }
+    for _ in 0..5 {
+        let center = random_point(rng, &input).unwrap();
+        if !in_stage_area(&input, &center) {
+            input.pillars.push(Pillar {
+                center,
+                radius: rng.gen_range(1..10) as f64,
+            });
+        }
+    }
+    CrossCheckCase::new(input, Solution::new(placements))
+}
+
+// 同じ大きさのケースでscoreとscore_fastにかかった時間の合計
+#[derive(Debug, Clone, Serialize)]
+pub struct Bench {
+    pub musicians: usize,
+    pub attendees: usize,
+    pub cases: usize,
+    pub score: Duration,
+    pub score_fast: Duration,
+}
+
+// 時間を測るついでに、2つの結果が一致するかも確かめる
+pub fn bench(cases: &[CrossCheckCase]) -> Result<Bench> {
+    let Some(first) = cases.first() else {
+        bail!("no cases to bench")
+    };
+    let mut result = Bench {
+        musicians: first.input.musicians.len(),
+        attendees: first.input.attendees.len(),
+        cases: cases.len(),
+        score: Duration::ZERO,
+        score_fast: Duration::ZERO,
+    };
+    for case in cases {
+        let now = Instant::now();
+        let exact = case.input.score(&case.solution.placements)?;
+        result.score += now.elapsed();
+        let now = Instant::now();
+        let fast = case.input.score_fast(&case.solution)?;
+        result.score_fast += now.elapsed();
+        if exact != fast {
+            bail!("score {} != score_fast {}", exact, fast);
+        }
+    }
+    Ok(result)
+}
+
+fn in_room(input: &Input, p: &Point) -> bool {
+    p.x() >= 0.0 && p.x() <= input.room_width && p.y() >= 0.0 && p.y() <= input.room_height
+}
+
+fn in_stage_area(input: &Input, p: &Point) -> bool {
+    let bl = input.stage_bottom_left;
+    p.x() >= bl.x()
+        && p.x() <= bl.x() + input.stage_width
+        && p.y() >= bl.y()
+        && p.y() <= bl.y() + input.stage_height
+}
+
+fn random_point<R: Rng>(rng: &mut R, input: &Input) -> Option<Point> {
+    Some(Point::new(
+        rng.gen_range(0.0..input.room_width),
+        rng.gen_range(0.0..input.room_height),
+    ))
+}
+
+// 2人のmusicianを通る直線上でステージの外に出たところ
+fn collinear_point<R: Rng>(rng: &mut R, input: &Input, placements: &[Point]) -> Option<Point> {
+    let a = *placements.choose(rng)?;
+    let b = *placements.choose(rng)?;
+    if a == b {
+        return None;
+    }
+    let d = (a - b) / a.euclidean_distance(&b);
+    let mut t = 10.0;
+    while in_room(input, &(a + d * t)) {
+        if !in_stage_area(input, &(a + d * t)) {
+            return Some(a + d * (t + rng.gen_range(0.0..10.0)));
+        }
+        t += 10.0;
+    }
+    None
+}
+
+// musician aへの視線がmusician bの半径5の円にちょうど接する位置
+fn tangent_point<R: Rng>(rng: &mut R, input: &Input, placements: &[Point]) -> Option<Point> {
+    let a = *placements.choose(rng)?;
+    let b = *placements.choose(rng)?;
+    if a == b {
+        return None;
+    }
+    let d = a.euclidean_distance(&b);
+    let theta = (b.y() - a.y()).atan2(b.x() - a.x())
+        + (5.0 / d).asin() * if rng.gen_bool(0.5) { 1.0 } else { -1.0 };
+    let dir = Point::new(theta.cos(), theta.sin());
+    let mut t = d;
+    while in_room(input, &(a + dir * t)) {
+        if !in_stage_area(input, &(a + dir * t)) {
+            return Some(a + dir * t);
+        }
+        t += 10.0;
+    }
+    None
+}
+
+// 水平・垂直な視線がmusicianからちょうど5離れる位置
+fn axis_tangent_point<R: Rng>(rng: &mut R, input: &Input, placements: &[Point]) -> Option<Point> {
+    let m = *placements.choose(rng)?;
+    let offset = if rng.gen_bool(0.5) { 5.0 } else { -5.0 };
+    let p = match rng.gen_range(0..4) {
+        0 => Point::new(
+            rng.gen_range(0.0..input.stage_bottom_left.x()),
+            m.y() + offset,
+        ),
+        1 => Point::new(
+            rng.gen_range(input.stage_bottom_left.x() + input.stage_width..input.room_width),
+            m.y() + offset,
+        ),
+        2 => Point::new(
+            m.x() + offset,
+            rng.gen_range(0.0..input.stage_bottom_left.y()),
+        ),
+        _ => Point::new(
+            m.x() + offset,
+            rng.gen_range(input.stage_bottom_left.y() + input.stage_height..input.room_height),
+        ),
+    };
+    Some(p)
+}
+
+// 参加者からmusicianへの視線にちょうど接するpillar
+fn tangent_pillar<R: Rng>(rng: &mut R, input: &Input, placements: &[Point]) -> Option<Pillar> {
+    let attendee = input.attendees.choose(rng)?.pos();
+    let m = *placements.choose(rng)?;
+    let len = attendee.euclidean_distance(&m);
+    let dir = (m - attendee) / len;
+    let normal = Point::new(-dir.y(), dir.x());
+    let radius = rng.gen_range(1..10) as f64;
+    let center = attendee + dir * (len * rng.gen_range(0.1..0.9)) + normal * radius;
+    Some(Pillar { center, radius })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand_pcg::Pcg64Mcg;
+
+    #[test]
+    fn bench_cases_agree() {
+        let mut rng = Pcg64Mcg::new(1);
+        let cases: Vec<_> = (0..3)
+            .map(|_| bench_case(&mut rng, 20, 50, ScoringRules::FULL))
+            .collect();
+        assert!(cases
+            .iter()
+            .all(|c| c.input.check_solution(&c.solution).is_ok()));
+        let result = bench(&cases).unwrap();
+        assert_eq!(
+            (result.musicians, result.attendees, result.cases),
+            (20, 50, 3)
+        );
+        assert!(bench(&[]).is_err());
+    }
+
+    #[test]
+    fn random_cases_agree() {
+        let mut rng = Pcg64Mcg::new(42);
+        for i in 0..500 {
+            let case = random_case(&mut rng, ScoringRules::FULL);
+            assert!(case.input.check_solution(&case.solution).is_ok());
+            if let Some(mismatch) = case.mismatch() {
+                let case = shrink(&case, |c| c.mismatch().is_some());
+                panic!(
+                    "case {}: {:?}\n{}",
+                    i,
+                    mismatch,
+                    serde_json::to_string(&case).unwrap()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn shrink_to_minimal_case() {
+        let mut rng = Pcg64Mcg::new(1);
+        let case = loop {
+            let case = random_case(&mut rng, ScoringRules::FULL);
+            if case.input.musicians.len() >= 4 && case.input.attendees.len() >= 3 {
+                break case;
+            }
+        };
+        let shrunk = shrink(&case, |c| {
+            c.input.musicians.len() >= 2 && !c.input.attendees.is_empty()
+        });
+        assert_eq!(shrunk.input.musicians.len(), 2);
+        assert_eq!(shrunk.solution.placements.len(), 2);
+        assert_eq!(shrunk.solution.volumes.as_ref().unwrap().len(), 2);
+        assert_eq!(shrunk.input.attendees.len(), 1);
+        assert!(shrunk.input.pillars.is_empty());
+    }
+}
@@ -1,16 +1,19 @@
-use crate::problem::{Input, Solution};
+use crate::annealing::{Delta, Move, State};
+use crate::problem::{Input, MAX_VOLUME};
 use geo::{EuclideanDistance, Point};
+use ordered_float::OrderedFloat;
 use rand::prelude::{IteratorRandom, SliceRandom};
 use rand::Rng;
 use rand_pcg::Pcg64Mcg;
-use std::collections::{HashSet, VecDeque};
+use std::collections::VecDeque;
 
 pub fn make_honeycomb_line(
-    input: &Input,
-    solution: &Solution,
+    state: &dyn State,
     rnd: &mut Pcg64Mcg,
-    musician_map: &Vec<Vec<usize>>,
-) -> Solution {
+    musician_map: &[Vec<usize>],
+) -> Option<Delta> {
+    let input = state.input();
+    let placements = state.placements();
     let target = (0..input.musicians.len()).choose(rnd).unwrap();
     let inst = input.musicians[target];
     let candidates = &musician_map[inst];
@@ -19,23 +22,24 @@ pub fn make_honeycomb_line(
         for j in i + 1..candidates.len() {
             let left = candidates[i];
             let right = candidates[j];
-            if solution.placements[left].euclidean_distance(&solution.placements[right]) < 15.0 {
+            if placements[left].euclidean_distance(&placements[right]) < 15.0 {
                 graph[left].push(right);
                 graph[right].push(left);
             }
         }
     }
     if graph[target].is_empty() {
-        return solution.clone();
+        return None;
     }
-    let mut cluster = HashSet::new();
+    // 見つけた順に並べる (HashSetの順番だと実行ごとに変わる)
+    let mut cluster = vec![];
     let mut queue = VecDeque::new();
     queue.push_back(target);
     while let Some(cur) = queue.pop_front() {
         if cluster.contains(&cur) {
             continue;
         }
-        cluster.insert(cur);
+        cluster.push(cur);
         if cluster.len() >= 7 {
             break;
         }
@@ -43,29 +47,28 @@ pub fn make_honeycomb_line(
             queue.push_back(next);
         }
     }
-    let cluster = cluster.into_iter().collect::<Vec<_>>();
     if cluster.len() < 2 {
-        return solution.clone();
+        return None;
     }
 
     let min_x = cluster
         .iter()
-        .map(|&i| solution.placements[i].x())
+        .map(|&i| placements[i].x())
         .min_by(|a, b| a.partial_cmp(b).unwrap())
         .unwrap();
     let max_x = cluster
         .iter()
-        .map(|&i| solution.placements[i].x())
+        .map(|&i| placements[i].x())
         .max_by(|a, b| a.partial_cmp(b).unwrap())
         .unwrap();
     let min_y = cluster
         .iter()
-        .map(|&i| solution.placements[i].y())
+        .map(|&i| placements[i].y())
         .min_by(|a, b| a.partial_cmp(b).unwrap())
         .unwrap();
     let max_y = cluster
         .iter()
-        .map(|&i| solution.placements[i].y())
+        .map(|&i| placements[i].y())
         .max_by(|a, b| a.partial_cmp(b).unwrap())
         .unwrap();
 
@@ -95,39 +98,43 @@ pub fn make_honeycomb_line(
                 points.push(Point::new(x, y));
             }
 
-            let mut new_solution = solution.clone();
-            for (&i, &p) in cluster.iter().zip(points.iter()) {
-                new_solution.placements[i] = p;
-            }
-            if input.is_valid_placements(&new_solution.placements).is_ok() {
-                println!("try honeycomb line: {:?}", points);
-                return new_solution;
+            let moves = cluster
+                .iter()
+                .cloned()
+                .zip(points.iter().cloned())
+                .collect::<Vec<_>>();
+            if input.is_valid_relocation(placements, &moves) {
+                return Some(Delta::relocate(state, &moves));
             }
         }
     }
-    //println!("failed to find honeycomb line");
-
-    solution.clone()
+    None
 }
 
 pub fn random_swap(
-    solution: &Solution,
-    musician_map: &Vec<Vec<usize>>,
+    state: &dyn State,
+    musician_map: &[Vec<usize>],
     rnd: &mut Pcg64Mcg,
-) -> (Solution, usize, usize) {
-    let mut new_solution = solution.clone();
+) -> Option<Delta> {
+    if musician_map.len() < 2 {
+        return None;
+    }
     let target_insts = (0..musician_map.len()).choose_multiple(rnd, 2);
-    let target_insts = (target_insts[0], target_insts[1]);
-    let left = *musician_map[target_insts.0].choose(rnd).unwrap();
-    let right = *musician_map[target_insts.1].choose(rnd).unwrap();
-    new_solution.placements.swap(left, right);
+    let left = *musician_map[target_insts[0]].choose(rnd)?;
+    let right = *musician_map[target_insts[1]].choose(rnd)?;
 
-    (new_solution, left, right)
+    Some(Delta::swap(state, left, right))
 }
 
-pub fn random_move2(input: &Input, solution: &Solution, rnd: &mut Pcg64Mcg) -> (Solution, usize) {
-    let target = (0..solution.placements.len()).choose(rnd).unwrap();
+pub fn random_move2(state: &dyn State, rnd: &mut Pcg64Mcg) -> Option<Delta> {
+    let target = (0..state.placements().len()).choose(rnd).unwrap();
     let delta = rnd.gen_range(0.0..1.0);
+    // 見つからなかった回数は焼きなましの統計 (failed) に出る
+    try_deltas(state, target, delta, rnd)
+}
+
+// targetをdeltaだけランダムな向きに動かす。だめなら向きを反転させてみる
+fn try_deltas(state: &dyn State, target: usize, delta: f64, rnd: &mut Pcg64Mcg) -> Option<Delta> {
     let theta = rnd.gen_range(0.0..2.0 * std::f64::consts::PI);
     let mut deltas = vec![
         [delta * f64::cos(theta), delta * f64::sin(theta)],
@@ -137,96 +144,128 @@ pub fn random_move2(input: &Input, solution: &Solution, rnd: &mut Pcg64Mcg) -> (
     ];
 
     deltas.shuffle(rnd);
+    let point = state.placements()[target];
     for &d in deltas.iter() {
-        let mut tmp_solution = solution.clone();
-        tmp_solution.placements[target] = Point::new(
-            solution.placements[target].x() + d[0],
-            solution.placements[target].y() + d[1],
-        );
-        if input.is_valid_placements(&tmp_solution.placements).is_ok() {
-            return (tmp_solution, target);
+        let moves = [(target, Point::new(point.x() + d[0], point.y() + d[1]))];
+        if state
+            .input()
+            .is_valid_relocation(state.placements(), &moves)
+        {
+            return Some(Delta::relocate(state, &moves));
         }
     }
-    println!("no valid delta move");
-
-    (solution.clone(), target)
+    None
 }
 
 // volumeが低いmusicianを遠くに移動させてみる
-pub fn random_move3(input: &Input, solution: &Solution, rnd: &mut Pcg64Mcg) -> (Solution, usize) {
-    if solution.volumes.is_none() {
-        return random_move2(input, solution, rnd);
-    }
-    let v0_candidates = solution
-        .volumes
-        .as_ref()
-        .unwrap()
-        .iter()
-        .enumerate()
-        .filter(|(_, &v)| v < 1.0)
-        .map(|(i, _)| i)
+pub fn random_move3(state: &dyn State, rnd: &mut Pcg64Mcg) -> Option<Delta> {
+    let v0_candidates = (0..state.placements().len())
+        .filter(|&i| state.volume(i) < 1.0)
         .collect::<Vec<_>>();
     if v0_candidates.is_empty() {
-        return random_move2(input, solution, rnd);
+        return random_move2(state, rnd);
     }
 
     let target = *v0_candidates.choose(rnd).unwrap();
     let delta = rnd.gen_range(10.0..100.0);
-    let theta = rnd.gen_range(0.0..2.0 * std::f64::consts::PI);
-    let mut deltas = vec![
-        [delta * f64::cos(theta), delta * f64::sin(theta)],
-        [-delta * f64::cos(theta), delta * f64::sin(theta)],
-        [delta * f64::cos(theta), -delta * f64::sin(theta)],
-        [-delta * f64::cos(theta), -delta * f64::sin(theta)],
-    ];
-    deltas.shuffle(rnd);
-    for &d in deltas.iter() {
-        let mut tmp_solution = solution.clone();
-        tmp_solution.placements[target] = Point::new(
-            solution.placements[target].x() + d[0],
-            solution.placements[target].y() + d[1],
-        );
-        if input.is_valid_placements(&tmp_solution.placements).is_ok() {
-            println!("big delta move");
-            return (tmp_solution, target);
+    try_deltas(state, target, delta, rnd)
+}
+
+// 負の嗜好の観客を何人見て、その中で一番嫌っている人の視線を遮るか
+const SHIELD_PICK_ATTENDEES: usize = 50;
+
+// fromからdirの向きに進んで、musicianを置ける長方形の縁に着くまでの長さ
+fn distance_to_edge(input: &Input, from: Point, dir: Point) -> Option<f64> {
+    let (min, max) = input.placeable_rect()?;
+    let mut t = f64::INFINITY;
+    for (p, d, lo, hi) in [
+        (from.x(), dir.x(), min.x(), max.x()),
+        (from.y(), dir.y(), min.y(), max.y()),
+    ] {
+        if d > 0.0 {
+            t = t.min((hi - p) / d);
+        } else if d < 0.0 {
+            t = t.min((lo - p) / d);
         }
     }
-    //println!("no valid delta move");
+    t.is_finite().then_some(t)
+}
+
+// volumeが0のmusicianを盾にする
+// 聞こえているmusicianと、それを嫌っている観客との視線がステージの縁を出るところに置いて遮る
+// 盾がいなければ誰かのvolumeを0にして盾にする
+pub fn random_shield(state: &dyn State, rnd: &mut Pcg64Mcg) -> Option<Delta> {
+    let input = state.input();
+    if !input.rules.volumes {
+        return None;
+    }
+    let placements = state.placements();
+    let (quiet, loud): (Vec<usize>, Vec<usize>) =
+        (0..placements.len()).partition(|&i| state.volume(i) < 1.0);
+    let target = *loud.choose(rnd)?;
+    let shield = match quiet.choose(rnd) {
+        Some(&shield) => shield,
+        None => *loud.iter().filter(|&&i| i != target).choose(rnd)?,
+    };
+    let instrument = input.musicians[target];
+    let attendee = (0..input.attendees.len())
+        .filter(|&a| input.attendees[a].tastes[instrument] < 0.0)
+        .choose_multiple(rnd, SHIELD_PICK_ATTENDEES)
+        .into_iter()
+        .filter(|&a| input.find_blocker(a, target, placements).is_none())
+        .min_by_key(|&a| OrderedFloat(input.raw_impact(a, target, &placements[target])))?;
 
-    return (solution.clone(), target);
+    let from = placements[target];
+    let to = input.attendees[attendee].pos();
+    let len = from.euclidean_distance(&to);
+    let dir = Point::new((to.x() - from.x()) / len, (to.y() - from.y()) / len);
+    let edge = distance_to_edge(input, from, dir)?;
+    // targetから10以上離れていないと置けない
+    if edge < 10.0 {
+        return None;
+    }
+    // 縁がふさがっていたら、視線上の内側を試す
+    let mut ts = vec![edge];
+    ts.extend((0..3).map(|_| rnd.gen_range(10.0..=edge)));
+    for t in ts {
+        let mut delta = Delta::relocate(state, &[(shield, from + dir * t)]);
+        delta.set_volume(state, shield, 0.0);
+        if delta.is_valid(state) {
+            return Some(delta);
+        }
+    }
+    None
 }
 
 pub fn random_move(
-    input: &Input,
-    solution: &Solution,
+    state: &dyn State,
     musician_map: &[Vec<usize>],
     rnd: &mut Pcg64Mcg,
-) -> (Solution, usize) {
-    let target = (0..solution.placements.len()).choose(rnd).unwrap();
+) -> Option<Delta> {
+    let input = state.input();
+    let target = (0..state.placements().len()).choose(rnd).unwrap();
     let inst = input.musicians[target];
     if musician_map[inst].len() == 1 {
-        return (solution.clone(), target);
+        return None;
     }
-    let mut candidates: HashSet<usize> = HashSet::from_iter(musician_map[inst].iter().cloned());
-    candidates.remove(&target);
-    let tar2 = candidates.iter().choose(rnd).unwrap();
+    let tar2 = *musician_map[inst]
+        .iter()
+        .filter(|&&m| m != target)
+        .choose(rnd)
+        .unwrap();
     let delta = rnd.gen_range(0.0..0.5);
-    let mut neighbors = find_neighbor(solution, *tar2, delta);
+    let mut neighbors = find_neighbor(state.placements(), tar2, delta);
     neighbors.shuffle(rnd);
     for &n in neighbors.iter() {
-        let mut tmp_solution = solution.clone();
-        tmp_solution.placements[target] = n;
-        if input.is_valid_placements(&tmp_solution.placements).is_ok() {
-            return (tmp_solution, target);
+        if input.is_valid_relocation(state.placements(), &[(target, n)]) {
+            return Some(Delta::relocate(state, &[(target, n)]));
         }
     }
-    println!("no valid neighbor");
-
-    (solution.clone(), target)
+    None
 }
 
-fn find_neighbor(solution: &Solution, target: usize, delta: f64) -> Vec<Point> {
-    let point = solution.placements[target];
+fn find_neighbor(placements: &[Point], target: usize, delta: f64) -> Vec<Point> {
+    let point = placements[target];
     let mut result = vec![];
     let dist = 10.0 + delta;
     result.push(Point::new(point.x() - dist, point.y()));
@@ -246,18 +285,308 @@ fn find_neighbor(solution: &Solution, target: usize, delta: f64) -> Vec<Point> {
     result
 }
 
-pub fn switch_volume(solution: &Solution, target: usize) -> Solution {
-    let mut new_solution = solution.clone();
-    let mut volumes = solution
-        .volumes
-        .clone()
-        .unwrap_or(vec![1.0; solution.placements.len()]);
-    if volumes[target] < 1.1 {
-        volumes[target] = 10.0;
+fn switched_volume(volume: f64) -> f64 {
+    if volume < 1.1 {
+        MAX_VOLUME
     } else {
-        volumes[target] = 0.0;
+        0.0
+    }
+}
+
+pub fn switch_volume(state: &dyn State, target: usize) -> Delta {
+    Delta::volume(state, target, switched_volume(state.volume(target)))
+}
+
+// 楽器ごとのmusicianの番号
+pub fn musician_map(input: &Input) -> Vec<Vec<usize>> {
+    let instruments = input.musicians.iter().max().map_or(0, |&m| m + 1);
+    let mut musician_map = vec![Vec::new(); instruments];
+    for (i, &m) in input.musicians.iter().enumerate() {
+        musician_map[m].push(i);
+    }
+    musician_map
+}
+
+// 以下は焼きなまし用に上の近傍をMoveにしたもの
+
+// 違う楽器のmusicianの位置を入れ替える
+pub struct SwapMove<'a> {
+    pub musician_map: &'a [Vec<usize>],
+}
+
+impl Move for SwapMove<'_> {
+    fn name(&self) -> &str {
+        "swap"
+    }
+
+    fn propose(&mut self, state: &dyn State, _temp: f64, rnd: &mut Pcg64Mcg) -> Option<Delta> {
+        random_swap(state, self.musician_map, rnd)
+    }
+}
+
+// 同じ楽器のmusicianの隣に動かす
+pub struct NeighborMove<'a> {
+    pub musician_map: &'a [Vec<usize>],
+}
+
+impl Move for NeighborMove<'_> {
+    fn name(&self) -> &str {
+        "move"
+    }
+
+    fn propose(&mut self, state: &dyn State, _temp: f64, rnd: &mut Pcg64Mcg) -> Option<Delta> {
+        random_move(state, self.musician_map, rnd)
     }
+}
+
+// 1未満だけ動かす
+pub struct DeltaMove;
+
+impl Move for DeltaMove {
+    fn name(&self) -> &str {
+        "delta move"
+    }
+
+    fn propose(&mut self, state: &dyn State, _temp: f64, rnd: &mut Pcg64Mcg) -> Option<Delta> {
+        random_move2(state, rnd)
+    }
+}
+
+// volumeが低いmusicianを遠くに動かす
+pub struct BigMove;
+
+impl Move for BigMove {
+    fn name(&self) -> &str {
+        "big move"
+    }
+
+    fn propose(&mut self, state: &dyn State, _temp: f64, rnd: &mut Pcg64Mcg) -> Option<Delta> {
+        random_move3(state, rnd)
+    }
+}
+
+// volumeが0のmusicianを、嫌われているmusicianへの視線をふさぐ位置に動かす
+pub struct ShieldMove;
+
+impl Move for ShieldMove {
+    fn name(&self) -> &str {
+        "shield"
+    }
+
+    fn propose(&mut self, state: &dyn State, _temp: f64, rnd: &mut Pcg64Mcg) -> Option<Delta> {
+        random_shield(state, rnd)
+    }
+}
+
+// 同じ楽器のかたまりを蜂の巣状の列に並べ直す
+pub struct HoneycombMove<'a> {
+    pub musician_map: &'a [Vec<usize>],
+}
+
+impl Move for HoneycombMove<'_> {
+    fn name(&self) -> &str {
+        "honeycomb"
+    }
+
+    fn propose(&mut self, state: &dyn State, _temp: f64, rnd: &mut Pcg64Mcg) -> Option<Delta> {
+        make_honeycomb_line(state, rnd, self.musician_map)
+    }
+}
 
-    new_solution.volumes = Some(volumes);
-    new_solution
+// ランダムなmusicianのvolumeを0と10で切り替える
+pub struct VolumeMove;
+
+impl Move for VolumeMove {
+    fn name(&self) -> &str {
+        "volume"
+    }
+
+    fn propose(&mut self, state: &dyn State, _temp: f64, rnd: &mut Pcg64Mcg) -> Option<Delta> {
+        if !state.input().rules.volumes {
+            return None;
+        }
+        let target = rnd.gen_range(0..state.placements().len());
+        Some(switch_volume(state, target))
+    }
+}
+
+// 動かしたmusicianのvolumeも1/2の確率でそれぞれ切り替える
+pub struct WithVolumeSwitch<M>(pub M);
+
+impl<M: Move> Move for WithVolumeSwitch<M> {
+    fn name(&self) -> &str {
+        self.0.name()
+    }
+
+    fn propose(&mut self, state: &dyn State, temp: f64, rnd: &mut Pcg64Mcg) -> Option<Delta> {
+        let mut delta = self.0.propose(state, temp, rnd)?;
+        if state.input().rules.volumes {
+            for m in delta.touched().collect::<Vec<_>>() {
+                if rnd.gen() {
+                    delta.set_volume(state, m, switched_volume(state.volume(m)));
+                }
+            }
+        }
+        Some(delta)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::annealing::*;
+    use crate::budget::Budget;
+    use crate::cross_check::random_case;
+    use crate::incremental::IncrementalScorer;
+    use crate::problem::ScoringRules;
+
+    #[test]
+    fn moves_apply_and_rollback() {
+        let mut rnd = Pcg64Mcg::new(11);
+        for _ in 0..50 {
+            let case = random_case(&mut rnd, ScoringRules::FULL);
+            let input = &case.input;
+            let musician_map = musician_map(input);
+            let mut moves: Vec<Box<dyn Move>> = vec![
+                Box::new(WithVolumeSwitch(SwapMove {
+                    musician_map: &musician_map,
+                })),
+                Box::new(WithVolumeSwitch(NeighborMove {
+                    musician_map: &musician_map,
+                })),
+                Box::new(WithVolumeSwitch(DeltaMove)),
+                Box::new(WithVolumeSwitch(BigMove)),
+                Box::new(HoneycombMove {
+                    musician_map: &musician_map,
+                }),
+                Box::new(VolumeMove),
+                Box::new(ShieldMove),
+            ];
+            let mut state = SolutionState::new(input, case.solution.clone()).unwrap();
+            let mut scorer = IncrementalScorer::new(input, &case.solution);
+            for _ in 0..10 {
+                let m = rnd.gen_range(0..moves.len());
+                let before = state.solution();
+                let Some(delta) = moves[m].propose(&state, 0.0, &mut rnd) else {
+                    continue;
+                };
+                assert!(state.apply(&delta), "{}", moves[m].name());
+                assert!(scorer.apply(&delta), "{}", moves[m].name());
+                let after = state.solution();
+                assert!(input.check_solution(&after).is_ok());
+                // 変わったmusicianはすべてtouchedに含まれる
+                for i in 0..after.placements.len() {
+                    if after.placements[i] != before.placements[i]
+                        || after.volume(i) != before.volume(i)
+                    {
+                        assert!(delta.touched().any(|t| t == i));
+                    }
+                }
+                assert_eq!(State::score(&state), input.score_fast(&after).unwrap());
+                assert_eq!(State::score(&scorer), State::score(&state));
+
+                if rnd.gen() {
+                    state.commit();
+                    State::commit(&mut scorer);
+                } else {
+                    state.rollback();
+                    State::rollback(&mut scorer);
+                    assert_eq!(state.solution().placements, before.placements);
+                    assert_eq!(state.solution().volumes(), before.volumes());
+                    assert_eq!(scorer.solution().placements, before.placements);
+                    assert_eq!(State::score(&scorer), input.score_fast(&before).unwrap());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn shield_blocks_negative_attendee() {
+        let input = Input {
+            room_width: 200.0,
+            room_height: 200.0,
+            stage_width: 100.0,
+            stage_height: 100.0,
+            stage_bottom_left: Point::new(50.0, 50.0),
+            musicians: vec![0, 1],
+            attendees: vec![crate::problem::Attendee {
+                x: 100.0,
+                y: 180.0,
+                tastes: vec![-1000.0, 0.0],
+            }],
+            pillars: vec![],
+            rules: ScoringRules::FULL,
+        };
+        let solution = crate::problem::Solution::with_volumes(
+            vec![Point::new(100.0, 100.0), Point::new(70.0, 70.0)],
+            Some(vec![MAX_VOLUME, 0.0]),
+        );
+        let mut state = SolutionState::new(&input, solution).unwrap();
+        assert!(State::score(&state) < 0);
+        let delta = random_shield(&state, &mut Pcg64Mcg::new(1)).unwrap();
+        // 視線がステージの縁を出るところに置く
+        assert_eq!(delta.moves(&state), vec![(1, Point::new(100.0, 140.0))]);
+        assert!(state.apply(&delta));
+        assert!(input.find_blocker(0, 0, state.placements()).is_some());
+        assert_eq!(State::score(&state), 0);
+
+        // volumeのルールがなければ盾は作れない
+        let mut lightning = input.clone();
+        lightning.rules = ScoringRules::LIGHTNING;
+        let state = SolutionState::new(&lightning, state.solution()).unwrap();
+        assert!(random_shield(&state, &mut Pcg64Mcg::new(1)).is_none());
+    }
+
+    // 同じseedと回数なら、スレッド数によらず同じ解になる
+    #[test]
+    fn runs_are_reproducible() {
+        let mut rnd = Pcg64Mcg::new(4);
+        let case = random_case(&mut rnd, ScoringRules::FULL);
+        let input = &case.input;
+        let musician_map = musician_map(input);
+        let run = |threads: usize| {
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .unwrap();
+            pool.install(|| {
+                let config = Config {
+                    acceptance: Acceptance::Metropolis,
+                    schedule: Schedule::Exponential {
+                        start: 1e5,
+                        end: 1.0,
+                    },
+                    selection: Selection::ADAPTIVE,
+                };
+                let mut annealer = Annealer::new(config)
+                    .with_move(
+                        WithVolumeSwitch(SwapMove {
+                            musician_map: &musician_map,
+                        }),
+                        1.0,
+                    )
+                    .with_move(
+                        WithVolumeSwitch(NeighborMove {
+                            musician_map: &musician_map,
+                        }),
+                        1.0,
+                    )
+                    .with_move(WithVolumeSwitch(DeltaMove), 1.0)
+                    .with_move(WithVolumeSwitch(BigMove), 1.0)
+                    .with_move(
+                        HoneycombMove {
+                            musician_map: &musician_map,
+                        },
+                        1.0,
+                    );
+                let mut scorer = IncrementalScorer::new(input, &case.solution);
+                let (_, solution) =
+                    annealer.run(&mut scorer, &mut Pcg64Mcg::new(9), &Budget::iterations(300));
+                serde_json::to_string(&solution).unwrap()
+            })
+        };
+        let expected = run(1);
+        assert_eq!(run(1), expected);
+        assert_eq!(run(4), expected);
+    }
 }
@@ -0,0 +1,498 @@
+// 配置やvolumeを少し変えたときのスコアを差分で計算する
+// 各(attendee, musician)の項をscore_fastと同じく個別にceilするので、結果はscore_fastと一致する
+use crate::annealing::{Delta, State};
+use crate::problem::*;
+use geo::{EuclideanDistance, Point};
+use rayon::prelude::*;
+use std::f64::consts::PI;
+
+// 角度での絞り込みは誤差の分だけ広めに取り、最後は線分との距離で判定する
+const ANGLE_EPS: f64 = 1e-9;
+
+// musician 1人分の状態。angles, coverは位置だけで決まり、rawは楽器にも依存する
+struct MusicianState {
+    // musicianから見た各attendeeの角度 (昇順)
+    angles: Vec<(f64, u32)>,
+    raw: Vec<f64>,
+    // attendeeへの視線を遮っているmusicianとpillarの数
+    cover: Vec<u32>,
+    volume: f64,
+    closeness: f64,
+    // 今のvolumeとMAX_VOLUMEでのスコア。volume 0なら常に0なので、この3つの切り替えはO(1)
+    score: Score,
+    max_score: Score,
+}
+
+impl MusicianState {
+    fn term(&self, attendee_id: usize, volume: f64) -> Score {
+        impact_score(volume, self.closeness, self.raw[attendee_id])
+    }
+
+    fn visible_score(&self, volume: f64) -> Score {
+        (0..self.raw.len())
+            .filter(|&a| self.cover[a] == 0)
+            .map(|a| self.term(a, volume))
+            .sum()
+    }
+
+    fn recompute_score(&mut self) {
+        self.max_score = self.visible_score(MAX_VOLUME);
+        self.update_volume_score();
+    }
+
+    // max_scoreは最新のものとして、今のvolumeでのscoreを決める
+    fn update_volume_score(&mut self) {
+        self.score = if self.volume == 0.0 {
+            0
+        } else if self.volume == MAX_VOLUME {
+            self.max_score
+        } else {
+            self.visible_score(self.volume)
+        };
+    }
+
+    // centerから見てangleを中心に±widthの範囲にいるattendee
+    fn attendees_in_cone(&self, angle: f64, width: f64, mut f: impl FnMut(usize)) {
+        let width = width + ANGLE_EPS;
+        if width >= PI {
+            self.angles.iter().for_each(|&(_, a)| f(a as usize));
+            return;
+        }
+        let mut visit = |lo: f64, hi: f64| {
+            let start = self.angles.partition_point(|&(t, _)| t < lo);
+            for &(t, a) in self.angles[start..].iter() {
+                if t > hi {
+                    break;
+                }
+                f(a as usize);
+            }
+        };
+        let (lo, hi) = (angle - width, angle + width);
+        visit(lo, hi);
+        if lo < -PI {
+            visit(lo + 2.0 * PI, PI);
+        }
+        if hi > PI {
+            visit(-PI, hi - 2.0 * PI);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Change {
+    Move(usize, Point),
+    Swap(usize, usize),
+    Volume(usize, f64),
+}
+
+pub struct IncrementalScorer {
+    input: Input,
+    placements: Vec<Point>,
+    volumes: Vec<f64>,
+    states: Vec<MusicianState>,
+    // commitしてからの変更。undoで逆順に戻す
+    history: Vec<Change>,
+}
+
+impl IncrementalScorer {
+    pub fn new(input: &Input, solution: &Solution) -> Self {
+        let placements = solution.placements.clone();
+        let volumes = solution.volumes();
+        let states = (0..placements.len())
+            .into_par_iter()
+            .map(|i| Self::create_state(input, &placements, i, input.volume(solution, i)))
+            .collect();
+        IncrementalScorer {
+            input: input.clone(),
+            placements,
+            volumes,
+            states,
+            history: vec![],
+        }
+    }
+
+    fn create_state(
+        input: &Input,
+        placements: &[Point],
+        musician_id: usize,
+        volume: f64,
+    ) -> MusicianState {
+        let pos = placements[musician_id];
+        let mut angles = input
+            .attendees
+            .iter()
+            .enumerate()
+            .map(|(a, attendee)| ((attendee.y - pos.y()).atan2(attendee.x - pos.x()), a as u32))
+            .collect::<Vec<_>>();
+        angles.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let mut state = MusicianState {
+            angles,
+            raw: (0..input.attendees.len())
+                .map(|a| input.raw_impact(a, musician_id, &pos))
+                .collect(),
+            cover: vec![0; input.attendees.len()],
+            volume,
+            closeness: input.playing_together_factor(musician_id, placements),
+            score: 0,
+            max_score: 0,
+        };
+        for (j, &p) in placements.iter().enumerate() {
+            if j != musician_id {
+                Self::add_blocker(input, &mut state, pos, p, BLOCKED_DIST, 1, false);
+            }
+        }
+        for pillar in input.active_pillars() {
+            Self::add_blocker(
+                input,
+                &mut state,
+                pos,
+                pillar.center,
+                pillar.radius,
+                1,
+                false,
+            );
+        }
+        state.recompute_score();
+        state
+    }
+
+    // posにいるmusicianの視線のうち、centerの円が遮るもののcoverをvalueだけ変える
+    // update_scoreなら見え方が変わった項をscoreに反映する
+    fn add_blocker(
+        input: &Input,
+        state: &mut MusicianState,
+        pos: Point,
+        center: Point,
+        radius: f64,
+        value: i32,
+        update_score: bool,
+    ) {
+        let d = pos.euclidean_distance(&center);
+        let width = if d > radius { (radius / d).asin() } else { PI };
+        let angle = (center.y() - pos.y()).atan2(center.x() - pos.x());
+        let mut hits = vec![];
+        state.attendees_in_cone(angle, width, |a| {
+            let segment = Segment {
+                p1: input.attendees[a].pos(),
+                p2: pos,
+            };
+            if segment.dist(&center) < radius {
+                hits.push(a);
+            }
+        });
+        for a in hits {
+            let before = state.cover[a];
+            state.cover[a] = (before as i32 + value) as u32;
+            if update_score {
+                let sign = if before == 0 && state.cover[a] > 0 {
+                    -1
+                } else if before > 0 && state.cover[a] == 0 {
+                    1
+                } else {
+                    continue;
+                };
+                state.score += sign * state.term(a, state.volume);
+                state.max_score += sign * state.term(a, MAX_VOLUME);
+            }
+        }
+    }
+
+    pub fn score(&self) -> Score {
+        self.states.iter().map(|s| s.score).sum()
+    }
+
+    // musician 1人が得ているスコア
+    pub fn musician_score(&self, musician_id: usize) -> Score {
+        self.states[musician_id].score
+    }
+
+    pub fn placements(&self) -> &[Point] {
+        &self.placements
+    }
+
+    pub fn solution(&self) -> Solution {
+        Solution::with_volumes(self.placements.clone(), Some(self.volumes.clone()))
+    }
+
+    pub fn is_valid_move(&self, musician_id: usize, new_point: Point) -> bool {
+        self.input
+            .is_valid_relocation(&self.placements, &[(musician_id, new_point)])
+    }
+
+    // 不正な位置なら何もせずfalseを返す
+    pub fn move_musician(&mut self, musician_id: usize, new_point: Point) -> bool {
+        if !self.is_valid_move(musician_id, new_point) {
+            return false;
+        }
+        let old_point = self.placements[musician_id];
+        self.apply_move(musician_id, new_point);
+        self.history.push(Change::Move(musician_id, old_point));
+        true
+    }
+
+    fn apply_move(&mut self, musician_id: usize, new_point: Point) {
+        let old_point = self.placements[musician_id];
+        let input = &self.input;
+        let placements = &self.placements;
+        self.states
+            .par_iter_mut()
+            .enumerate()
+            .filter(|(m, _)| *m != musician_id)
+            .for_each(|(m, state)| {
+                let pos = placements[m];
+                Self::add_blocker(input, state, pos, old_point, BLOCKED_DIST, -1, true);
+                Self::add_blocker(input, state, pos, new_point, BLOCKED_DIST, 1, true);
+            });
+        self.placements[musician_id] = new_point;
+        let volume = self.states[musician_id].volume;
+        self.states[musician_id] =
+            Self::create_state(&self.input, &self.placements, musician_id, volume);
+        self.update_closeness(self.input.musicians[musician_id]);
+    }
+
+    // 楽器instrumentのmusicianのPlaying togetherの倍率を計算し直す
+    fn update_closeness(&mut self, instrument: usize) {
+        if !self.input.rules.playing_together {
+            return;
+        }
+        let input = &self.input;
+        let placements = &self.placements;
+        self.states
+            .par_iter_mut()
+            .enumerate()
+            .filter(|(m, _)| input.musicians[*m] == instrument)
+            .for_each(|(m, state)| {
+                let closeness = input.playing_together_factor(m, placements);
+                if closeness != state.closeness {
+                    state.closeness = closeness;
+                    state.recompute_score();
+                }
+            });
+    }
+
+    // 2人の位置を入れ替える。位置の集合は変わらないので他のmusicianの見え方は変わらない
+    pub fn swap_musicians(&mut self, i: usize, j: usize) {
+        if i == j {
+            return;
+        }
+        self.apply_swap(i, j);
+        self.history.push(Change::Swap(i, j));
+    }
+
+    fn apply_swap(&mut self, i: usize, j: usize) {
+        self.placements.swap(i, j);
+        // 位置に紐づくangles, coverは入れ替えるだけでよい
+        let (a, b) = if i < j { (i, j) } else { (j, i) };
+        let (left, right) = self.states.split_at_mut(b);
+        let (sa, sb) = (&mut left[a], &mut right[0]);
+        std::mem::swap(&mut sa.angles, &mut sb.angles);
+        std::mem::swap(&mut sa.cover, &mut sb.cover);
+        // 同じ楽器同士なら倍率も位置と一緒に入れ替わる
+        std::mem::swap(&mut sa.closeness, &mut sb.closeness);
+        for m in [i, j] {
+            let pos = self.placements[m];
+            let state = &mut self.states[m];
+            state.raw = (0..self.input.attendees.len())
+                .map(|a| self.input.raw_impact(a, m, &pos))
+                .collect();
+            state.recompute_score();
+        }
+        let (inst_i, inst_j) = (self.input.musicians[i], self.input.musicians[j]);
+        if inst_i != inst_j {
+            self.update_closeness(inst_i);
+            self.update_closeness(inst_j);
+        }
+    }
+
+    // 範囲外のvolumeなら何もせずfalseを返す
+    pub fn set_volume(&mut self, musician_id: usize, volume: f64) -> bool {
+        if !(0.0..=MAX_VOLUME).contains(&volume) {
+            return false;
+        }
+        let old = self.volumes[musician_id];
+        self.apply_volume(musician_id, volume);
+        self.history.push(Change::Volume(musician_id, old));
+        true
+    }
+
+    fn apply_volume(&mut self, musician_id: usize, volume: f64) {
+        self.volumes[musician_id] = volume;
+        let state = &mut self.states[musician_id];
+        state.volume = if self.input.rules.volumes {
+            volume
+        } else {
+            DEFAULT_VOLUME
+        };
+        state.update_volume_score();
+    }
+
+    pub fn volume(&self, musician_id: usize) -> f64 {
+        self.volumes[musician_id]
+    }
+
+    // 直前の変更を取り消す。取り消せるものがなければfalse
+    pub fn undo(&mut self) -> bool {
+        match self.history.pop() {
+            Some(Change::Move(m, p)) => self.apply_move(m, p),
+            Some(Change::Swap(i, j)) => self.apply_swap(i, j),
+            Some(Change::Volume(m, v)) => self.apply_volume(m, v),
+            None => return false,
+        }
+        true
+    }
+
+    // ここまでの変更を確定し、undoの履歴を捨てる
+    pub fn commit(&mut self) {
+        self.history.clear();
+    }
+
+    // 複数人をまとめて動かす。途中で重ならないように、今いる場所と離れている移動先から動かす
+    fn apply_moves(&mut self, mut moves: Vec<(usize, Point)>) {
+        while !moves.is_empty() {
+            let clearance = |&(m, p): &(usize, Point)| {
+                self.placements
+                    .iter()
+                    .enumerate()
+                    .filter(|&(j, _)| j != m)
+                    .map(|(_, q)| p.euclidean_distance(q))
+                    .fold(f64::INFINITY, f64::min)
+            };
+            let k = (0..moves.len())
+                .max_by(|&a, &b| clearance(&moves[a]).total_cmp(&clearance(&moves[b])))
+                .unwrap();
+            let (m, p) = moves.swap_remove(k);
+            let old_point = self.placements[m];
+            self.apply_move(m, p);
+            self.history.push(Change::Move(m, old_point));
+        }
+    }
+}
+
+impl State for IncrementalScorer {
+    fn input(&self) -> &Input {
+        &self.input
+    }
+
+    fn placements(&self) -> &[Point] {
+        &self.placements
+    }
+
+    fn volume(&self, musician_id: usize) -> f64 {
+        self.volumes[musician_id]
+    }
+
+    fn score(&self) -> Score {
+        IncrementalScorer::score(self)
+    }
+
+    fn apply(&mut self, delta: &Delta) -> bool {
+        if !delta.is_valid(self) {
+            return false;
+        }
+        let moves = delta.moves(self);
+        // 2人の入れ替えは位置の集合が変わらないのでswapで済ませる
+        if let [(i, p), (j, q)] = moves[..] {
+            if p == self.placements[j] && q == self.placements[i] {
+                self.swap_musicians(i, j);
+            } else {
+                self.apply_moves(moves);
+            }
+        } else {
+            self.apply_moves(moves);
+        }
+        for c in delta.changes.iter() {
+            if self.volumes[c.musician] != c.volume {
+                self.set_volume(c.musician, c.volume);
+            }
+        }
+        true
+    }
+
+    fn commit(&mut self) {
+        IncrementalScorer::commit(self)
+    }
+
+    fn rollback(&mut self) {
+        while self.undo() {}
+    }
+
+    fn solution(&self) -> Solution {
+        IncrementalScorer::solution(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cross_check::random_case;
+    use rand::Rng;
+    use rand_pcg::Pcg64Mcg;
+
+    fn check(scorer: &IncrementalScorer, input: &Input) {
+        let solution = scorer.solution();
+        assert_eq!(scorer.score(), input.score_fast(&solution).unwrap());
+    }
+
+    #[test]
+    fn random_operations_match_score_fast() {
+        let mut rng = Pcg64Mcg::new(11);
+        for _ in 0..50 {
+            let case = random_case(&mut rng, ScoringRules::FULL);
+            let input = &case.input;
+            let n = input.musicians.len();
+            let mut scorer = IncrementalScorer::new(input, &case.solution);
+            check(&scorer, input);
+            let initial = scorer.score();
+            for _ in 0..30 {
+                let i = rng.gen_range(0..n);
+                match rng.gen_range(0..3) {
+                    0 => {
+                        let p = scorer.placements()[i]
+                            + Point::new(rng.gen_range(-20.0..20.0), rng.gen_range(-20.0..20.0));
+                        scorer.move_musician(i, p);
+                    }
+                    1 => scorer.swap_musicians(i, rng.gen_range(0..n)),
+                    _ => {
+                        // 0とMAX_VOLUMEはキャッシュから、それ以外は数え直す
+                        let volumes = [0.0, MAX_VOLUME, rng.gen_range(0.0..=MAX_VOLUME)];
+                        scorer.set_volume(i, volumes[rng.gen_range(0..3)]);
+                    }
+                }
+                check(&scorer, input);
+            }
+            while scorer.undo() {}
+            assert_eq!(scorer.score(), initial);
+            assert_eq!(scorer.placements(), &case.solution.placements[..]);
+        }
+    }
+
+    #[test]
+    fn problem_moves_match_score_fast() {
+        let mut input = crate::read_input("./testdata/problem-80.json", None).unwrap();
+        input.attendees.truncate(300);
+        let solution_str = std::fs::read_to_string("./testdata/solution-80.json").unwrap();
+        let solution: Solution = serde_json::from_str(&solution_str).unwrap();
+        let mut scorer = IncrementalScorer::new(&input, &solution);
+        check(&scorer, &input);
+
+        let mut rng = Pcg64Mcg::new(5);
+        let n = input.musicians.len();
+        let mut moved = 0;
+        while moved < 20 {
+            let i = rng.gen_range(0..n);
+            let p = scorer.placements()[i]
+                + Point::new(rng.gen_range(-5.0..5.0), rng.gen_range(-5.0..5.0));
+            if scorer.move_musician(i, p) {
+                moved += 1;
+                scorer.commit();
+            }
+        }
+        scorer.swap_musicians(0, n - 1);
+        scorer.set_volume(1, 0.0);
+        check(&scorer, &input);
+        assert!(scorer.undo());
+        assert!(scorer.undo());
+        assert!(!scorer.undo());
+        check(&scorer, &input);
+    }
+}
@@ -1,16 +1,42 @@
 pub mod problem;
 
+#[cfg(not(target_arch = "wasm32"))]
+pub mod annealing;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod archive;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod batch;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod budget;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod checkpoint;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod cross_check;
 #[cfg(not(target_arch = "wasm32"))]
 pub mod garasubo_util;
 #[cfg(not(target_arch = "wasm32"))]
+pub mod incremental;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod optimize;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod pipeline;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod server;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod solve;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod solver_util;
+pub mod spatial;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod stats;
 #[cfg(target_arch = "wasm32")]
 mod wasm_util;
 
 use anyhow::Context;
-use geo::Point;
 use problem::*;
 
+#[cfg(not(target_arch = "wasm32"))]
+use geo::Point;
 #[cfg(not(target_arch = "wasm32"))]
 use rand::prelude::SliceRandom;
 #[cfg(not(target_arch = "wasm32"))]
@@ -18,8 +44,6 @@ use rand::Rng;
 #[cfg(not(target_arch = "wasm32"))]
 use rand_pcg::Pcg64Mcg;
 
-#[cfg(target_arch = "wasm32")]
-use crate::wasm_util::Placement;
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
 
@@ -61,51 +85,63 @@ pub fn calc_score(
     volumes: Option<Vec<f64>>,
     is_full: bool,
 ) -> Result<f64, JsValue> {
-    use crate::problem::Attendee;
-    use geo::Point;
-
-    let solution = problem::Solution {
-        placements: placement
-            .iter()
-            .map(|p| {
-                let p: Placement = serde_wasm_bindgen::from_value(p.into()).unwrap();
-                Point::new(p.x, p.y)
-            })
-            .collect(),
-        volumes,
-    };
-    let input = problem::Input {
+    let (input, solution) = wasm_util::to_input_and_solution(
         room_width,
         room_height,
         stage_width,
         stage_height,
-        stage_bottom_left: Point::new(stage_bottom_left[0], stage_bottom_left[1]),
+        stage_bottom_left,
         musicians,
-        attendees: attendees
-            .iter()
-            .map(|a| {
-                let a: Attendee = serde_wasm_bindgen::from_value(a.into()).unwrap();
-                a
-            })
-            .collect(),
-        pillars: pillars
-            .iter()
-            .map(|p| {
-                let p: crate::wasm_util::Pillar = serde_wasm_bindgen::from_value(p.into()).unwrap();
-
-                Pillar {
-                    center: Point::new(p.center[0], p.center[1]),
-                    radius: p.radius,
-                }
-            })
-            .collect(),
-    };
+        attendees,
+        pillars,
+        placement,
+        volumes,
+        is_full,
+    );
 
     solution
         .score(&input)
+        .map(|score| score as f64)
         .map_err(|e| JsValue::from_str(&format!("{}", e)))
 }
 
+// 制約違反をすべて返す。違反がなければ空配列
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn validate_solution(
+    room_width: f64,
+    room_height: f64,
+    stage_width: f64,
+    stage_height: f64,
+    stage_bottom_left: Vec<f64>,
+    musicians: Vec<usize>,
+    attendees: Vec<JsValue>,
+    pillars: Vec<JsValue>,
+    placement: Vec<JsValue>,
+    volumes: Option<Vec<f64>>,
+    is_full: bool,
+) -> Result<JsValue, JsValue> {
+    let (input, solution) = wasm_util::to_input_and_solution(
+        room_width,
+        room_height,
+        stage_width,
+        stage_height,
+        stage_bottom_left,
+        musicians,
+        attendees,
+        pillars,
+        placement,
+        volumes,
+        is_full,
+    );
+
+    let errors = match input.check_solution(&solution) {
+        Ok(()) => vec![],
+        Err(errors) => errors.0,
+    };
+    serde_wasm_bindgen::to_value(&errors).map_err(|e| JsValue::from_str(&format!("{}", e)))
+}
+
 #[cfg(target_arch = "wasm32")]
 #[wasm_bindgen]
 pub fn add(left: usize, right: usize) -> usize {
@@ -202,21 +238,7 @@ impl<'a> PlacementGenerator<'a> {
     }
 }
 
-pub fn get_time() -> f64 {
-    static mut STIME: f64 = -1.0;
-    let t = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap();
-    let ms = t.as_secs() as f64 + t.subsec_nanos() as f64 * 1e-9;
-    unsafe {
-        if STIME < 0.0 {
-            STIME = ms;
-        }
-        ms - STIME
-    }
-}
-
-fn get_id(input: &str) -> anyhow::Result<i32> {
+pub fn get_id(input: &str) -> anyhow::Result<i32> {
     let path = std::path::Path::new(&input);
     let base = path.file_stem().context("no file_stem?")?;
     let base = base.to_str().context("to_str failed")?;
@@ -229,7 +251,34 @@ fn get_id(input: &str) -> anyhow::Result<i32> {
 
 pub fn is_lightning(path: &str) -> anyhow::Result<bool> {
     let id = get_id(path)?;
-    Ok(id <= 55)
+    Ok(id <= LAST_LIGHTNING_PROBLEM_ID)
+}
+
+// 問題JSONを読み込み採点ルールを設定する
+// rulesが指定されなければファイル名の問題IDから決める
+pub fn read_input(path: &str, rules: Option<ScoringRules>) -> anyhow::Result<Input> {
+    let input_str =
+        std::fs::read_to_string(path).with_context(|| format!("failed to read {path}"))?;
+    let mut input: Input =
+        serde_json::from_str(&input_str).with_context(|| format!("failed to parse {path}"))?;
+    input.rules = match rules {
+        Some(rules) => rules,
+        None => match get_id(path) {
+            Ok(id) => ScoringRules::for_problem_id(id),
+            Err(_) => {
+                let rules = ScoringRules::official(!input.pillars.is_empty());
+                eprintln!("no problem id in {path}; guessed scoring rules: {rules}");
+                rules
+            }
+        },
+    };
+    Ok(input)
+}
+
+pub fn read_solution(path: &str) -> anyhow::Result<Solution> {
+    let solution_str =
+        std::fs::read_to_string(path).with_context(|| format!("failed to read {path}"))?;
+    serde_json::from_str(&solution_str).with_context(|| format!("failed to parse {path}"))
 }
 
 #[cfg(test)]
@@ -246,4 +295,15 @@ mod tests {
         assert!(is_lightning("../problems/problem-55.json").unwrap());
         assert!(!is_lightning("../problems/problem-56.json").unwrap());
     }
+
+    #[test]
+    fn test_read_input_rules() {
+        let input = read_input("./testdata/problem-80.json", None).unwrap();
+        assert_eq!(input.rules, ScoringRules::FULL);
+        let input = read_input("./testdata/problem-29.json", None).unwrap();
+        assert_eq!(input.rules, ScoringRules::official(false));
+        let input =
+            read_input("./testdata/problem-29.json", Some(ScoringRules::LIGHTNING)).unwrap();
+        assert_eq!(input.rules, ScoringRules::LIGHTNING);
+    }
 }
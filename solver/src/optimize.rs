@@ -0,0 +1,420 @@
+// 既存の解を良くする戦略。solverコマンドの`optimize <strategy>`から呼ぶ
+use crate::annealing::*;
+use crate::budget::Budget;
+use crate::checkpoint::{BestOutput, Checkpoint};
+use crate::garasubo_util::{self, *};
+use crate::incremental::IncrementalScorer;
+use crate::problem::*;
+use crate::solver_util::{reduce_attendees, volume_optimize, volume_optimize_fast, yamanobori};
+use anyhow::{bail, Result};
+use geo::Point;
+use rand::Rng;
+use rand_pcg::Pcg64Mcg;
+use rayon::prelude::*;
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Optimizer {
+    // いろいろな近傍で焼きなます
+    Optimizer,
+    // 蜂の巣状に並べ直してから、8並列で焼きなます
+    GarasuboYakinamashi,
+    // 間引いた観客で、1人ずつ上下左右に動かして焼きなます
+    Fuji,
+    // 間引いた観客で山登りする
+    Yamanobori,
+    // volumeだけを決め直す
+    VolumeOptimize,
+}
+
+impl Optimizer {
+    pub const ALL: [Optimizer; 5] = [
+        Optimizer::Optimizer,
+        Optimizer::GarasuboYakinamashi,
+        Optimizer::Fuji,
+        Optimizer::Yamanobori,
+        Optimizer::VolumeOptimize,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Optimizer::Optimizer => "optimizer",
+            Optimizer::GarasuboYakinamashi => "garasubo_yakinamashi",
+            Optimizer::Fuji => "fuji",
+            Optimizer::Yamanobori => "yamanobori",
+            Optimizer::VolumeOptimize => "volume_optimize",
+        }
+    }
+}
+
+impl fmt::Display for Optimizer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Optimizer {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match Optimizer::ALL.iter().find(|o| o.name() == s) {
+            Some(&o) => Ok(o),
+            None => bail!(
+                "unknown optimizer: {s} (one of {})",
+                Optimizer::ALL.map(|o| o.name()).join(", ")
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Options {
+    pub seed: u128,
+    // optimizerとgarasubo_yakinamashiの受理規則と温度
+    pub acceptance: Acceptance,
+    pub temp_start: f64,
+    pub temp_end: f64,
+    pub selection: Selection,
+    // fujiの温度の減衰率
+    pub alpha: f64,
+    // fujiとyamanoboriで使う観客の数
+    pub reduced_attendees: Option<usize>,
+    // 焼きなましのチェックポイント。並列に回すものは<path>.<worker>に書く
+    pub checkpoint: Option<String>,
+    pub checkpoint_interval: Duration,
+    pub resume: bool,
+    // optimizerでShieldMoveも使う
+    pub shield: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            seed: 0,
+            acceptance: Acceptance::Greedy,
+            temp_start: 10000.0,
+            temp_end: 10.0,
+            selection: Selection::ADAPTIVE,
+            alpha: 0.98,
+            reduced_attendees: None,
+            checkpoint: None,
+            checkpoint_interval: Duration::from_secs(60),
+            resume: false,
+            shield: false,
+        }
+    }
+}
+
+impl Options {
+    fn checkpoint_path(&self, worker: Option<u128>) -> Option<String> {
+        let path = self.checkpoint.as_ref()?;
+        Some(match worker {
+            Some(worker) => format!("{}.{}", path, worker),
+            None => path.clone(),
+        })
+    }
+
+    // 再開するならチェックポイントを読む
+    fn resume_checkpoint(&self, path: &Option<String>) -> Result<Option<Checkpoint>> {
+        match path {
+            Some(path) if self.resume => Ok(Some(Checkpoint::load(path)?)),
+            _ => Ok(None),
+        }
+    }
+
+    fn with_checkpoint<'a>(
+        &self,
+        mut annealer: Annealer<'a>,
+        path: &Option<String>,
+        resume: Option<Checkpoint>,
+        output: Option<&'a BestOutput<'a>>,
+    ) -> Annealer<'a> {
+        if let Some(path) = path {
+            annealer = annealer.with_checkpoint(path, self.checkpoint_interval);
+        }
+        if let Some(checkpoint) = resume {
+            annealer = annealer.resume_from(checkpoint);
+        }
+        if let Some(output) = output {
+            annealer = annealer.with_output(output);
+        }
+        annealer
+    }
+}
+
+// solutionを良くした解を返す。悪くなったり正しくなくなったりしたらsolutionをそのまま返す
+// outputがあれば、途中で良くなるたびに書き出す
+pub fn optimize(
+    input: &Input,
+    solution: &Solution,
+    optimizer: Optimizer,
+    options: &Options,
+    budget: &Budget,
+    output: Option<&BestOutput>,
+) -> Result<Solution> {
+    if let Err(errors) = input.check_solution(solution) {
+        bail!("invalid solution: {}", errors);
+    }
+    let result = match optimizer {
+        Optimizer::Optimizer => anneal(input, solution, options, budget, output)?,
+        Optimizer::GarasuboYakinamashi => yakinamashi(input, solution, options, budget, output)?,
+        Optimizer::Fuji => fuji(input, solution, options, budget, output)?,
+        Optimizer::Yamanobori => {
+            let reduce_num = options.reduced_attendees.unwrap_or(input.attendees.len());
+            let solution = yamanobori(
+                input,
+                &mut solution.placements.clone(),
+                &solution.volumes(),
+                budget,
+                options.seed,
+                reduce_num,
+            );
+            volume_optimize(input, &solution)
+        }
+        Optimizer::VolumeOptimize => volume_optimize(input, solution),
+    };
+
+    if let Err(errors) = input.check_solution(&result) {
+        eprintln!("{} made an invalid solution: {}", optimizer, errors);
+        return Ok(solution.clone());
+    }
+    if input.score_fast(&result)? < input.score_fast(solution)? {
+        return Ok(solution.clone());
+    }
+    Ok(result)
+}
+
+// スコアが同じなら番号の小さいworkerの解を選び、スレッド数によらず同じ結果にする
+fn best_of_workers(
+    workers: u128,
+    run: impl Fn(u128) -> Result<(Score, Solution)> + Sync,
+) -> Result<Solution> {
+    let results = (0..workers)
+        .into_par_iter()
+        .map(|i| run(i).map(|(score, solution)| (i, score, solution)))
+        .collect::<Result<Vec<_>>>()?;
+    let (_, best_score, best_solution) = results
+        .into_iter()
+        .max_by(|a, b| a.1.cmp(&b.1).then(b.0.cmp(&a.0)))
+        .unwrap();
+    eprintln!("final best score: {}", best_score);
+    Ok(best_solution)
+}
+
+fn anneal_config(options: &Options) -> Config {
+    Config {
+        acceptance: options.acceptance,
+        schedule: Schedule::Exponential {
+            start: options.temp_start,
+            end: options.temp_end,
+        },
+        selection: options.selection,
+    }
+}
+
+const OPTIMIZER_WORKERS: u128 = 2;
+
+fn anneal(
+    input: &Input,
+    solution: &Solution,
+    options: &Options,
+    budget: &Budget,
+    output: Option<&BestOutput>,
+) -> Result<Solution> {
+    let musician_map = garasubo_util::musician_map(input);
+    if musician_map.len() < 2 {
+        bail!("musicians are too few");
+    }
+    let musician_map = &musician_map;
+    let solution = volume_optimize(input, solution);
+    best_of_workers(OPTIMIZER_WORKERS, |worker| {
+        let seed = options.seed + worker * 4;
+        eprintln!("my seed is {}", seed);
+        let mut rnd = Pcg64Mcg::new(seed);
+        let path = options.checkpoint_path(Some(worker));
+        let resume = options.resume_checkpoint(&path)?;
+        let start = resume.as_ref().map_or(&solution, |c| &c.solution);
+        let mut state = SolutionState::new(input, start.clone())?;
+        let annealer = Annealer::new(anneal_config(options))
+            .with_move(WithVolumeSwitch(SwapMove { musician_map }), 1.0)
+            .with_move(WithVolumeSwitch(NeighborMove { musician_map }), 1.0)
+            .with_move(WithVolumeSwitch(DeltaMove), 1.0)
+            .with_move(WithVolumeSwitch(BigMove), 2.0)
+            .with_move(HoneycombMove { musician_map }, 1.0)
+            .with_move(VolumeMove, 1.0);
+        let annealer = if options.shield {
+            annealer.with_move(ShieldMove, 1.0)
+        } else {
+            annealer
+        };
+        let mut annealer = options.with_checkpoint(annealer, &path, resume, output);
+        let result = annealer.run(&mut state, &mut rnd, budget);
+        eprintln!("{}", annealer.stats());
+        Ok(result)
+    })
+}
+
+const YAKINAMASHI_WORKERS: u128 = 8;
+
+fn yakinamashi(
+    input: &Input,
+    solution: &Solution,
+    options: &Options,
+    budget: &Budget,
+    output: Option<&BestOutput>,
+) -> Result<Solution> {
+    let musician_map = garasubo_util::musician_map(input);
+    if musician_map.len() < 2 {
+        bail!("musicians are too few");
+    }
+    let musician_map = &musician_map;
+    let solution = volume_optimize_fast(input, solution);
+    best_of_workers(YAKINAMASHI_WORKERS, |worker| {
+        let seed = options.seed + worker * 4;
+        eprintln!("my seed is {}", seed);
+        let mut rnd = Pcg64Mcg::new(seed);
+        let path = options.checkpoint_path(Some(worker));
+        let resume = options.resume_checkpoint(&path)?;
+        // 最初に蜂の巣状に並べ直し、volumeを付け直してから始める。再開するときはそのまま
+        let start = match &resume {
+            Some(checkpoint) => checkpoint.solution.clone(),
+            None => {
+                let mut start = solution.clone();
+                let state = SolutionState::new(input, start.clone())?;
+                if let Some(delta) = make_honeycomb_line(&state, &mut rnd, musician_map) {
+                    delta.apply_to(&mut start);
+                }
+                volume_optimize_fast(input, &start)
+            }
+        };
+
+        let mut state = SolutionState::new(input, start)?;
+        let annealer = Annealer::new(anneal_config(options))
+            .with_move(WithVolumeSwitch(SwapMove { musician_map }), 1.0)
+            .with_move(WithVolumeSwitch(NeighborMove { musician_map }), 1.0)
+            .with_move(WithVolumeSwitch(DeltaMove), 1.0)
+            .with_move(WithVolumeSwitch(BigMove), 1.0);
+        let mut annealer = options.with_checkpoint(annealer, &path, resume, output);
+        let result = annealer.run(&mut state, &mut rnd, budget);
+        eprintln!("{}", annealer.stats());
+        Ok(result)
+    })
+}
+
+const TEMP_MIN: f64 = 0.00001;
+
+// 上下左右に動かす。温度が下がったら細かく動かす
+struct StepMove {
+    fine_temp: f64,
+}
+
+impl Move for StepMove {
+    fn name(&self) -> &str {
+        "step"
+    }
+
+    fn propose(&mut self, state: &dyn State, temp: f64, rng: &mut Pcg64Mcg) -> Option<Delta> {
+        let idx = rng.gen_range(0..state.placements().len());
+        let dir = rng.gen_range(0..4);
+        let dx = [0.0, 1.0, 0.0, -1.0];
+        let dy = [1.0, 0.0, -1.0, 0.0];
+        let step = if temp > self.fine_temp {
+            rng.gen_range(1..100) as f64 / 10.0
+        } else {
+            rng.gen_range(1..100) as f64 / 100.0
+        };
+        let new_point = state.placements()[idx] + Point::new(dx[dir] * step, dy[dir] * step);
+        Some(Delta::relocate(state, &[(idx, new_point)]))
+    }
+}
+
+// 間引いた観客で焼きなます。outputには元の観客でのスコアが良くなったときだけ書き出す
+fn fuji(
+    input: &Input,
+    solution: &Solution,
+    options: &Options,
+    budget: &Budget,
+    output: Option<&BestOutput>,
+) -> Result<Solution> {
+    let mut rng = Pcg64Mcg::new(options.seed);
+    let config = Config {
+        acceptance: Acceptance::Metropolis,
+        schedule: Schedule::Geometric {
+            start: 1.0,
+            alpha: options.alpha,
+            min: TEMP_MIN,
+        },
+        selection: Selection::Fixed,
+    };
+    let path = options.checkpoint_path(None);
+    let resume = options.resume_checkpoint(&path)?;
+    let start = resume.as_ref().map_or(solution, |c| &c.solution);
+    let reduce_num = options.reduced_attendees.unwrap_or(input.attendees.len());
+    let reduced = reduce_attendees(input, reduce_num);
+    let mut scorer = IncrementalScorer::new(
+        &reduced,
+        &Solution::with_volumes(start.placements.clone(), Some(start.volumes())),
+    );
+
+    let annealer = Annealer::new(config).with_move(
+        StepMove {
+            fine_temp: TEMP_MIN * 10.0,
+        },
+        1.0,
+    );
+    let mut annealer = options.with_checkpoint(annealer, &path, resume, output);
+    let (best_score, best_solution) = annealer.run(&mut scorer, &mut rng, budget);
+    eprintln!("{}", annealer.stats());
+    if best_score <= annealer.stats().initial_score {
+        eprintln!("no improvement");
+    }
+
+    let solution = Solution {
+        placements: best_solution.placements,
+        volumes: solution.volumes.clone(),
+    };
+    Ok(volume_optimize(input, &solution))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cross_check::random_case;
+    use crate::problem::ScoringRules;
+
+    #[test]
+    fn parse_optimizer() {
+        for optimizer in Optimizer::ALL {
+            assert_eq!(optimizer.name().parse::<Optimizer>().unwrap(), optimizer);
+        }
+        assert!("sa".parse::<Optimizer>().is_err());
+    }
+
+    #[test]
+    fn optimizers_never_make_it_worse() {
+        let mut rng = Pcg64Mcg::new(11);
+        let case = random_case(&mut rng, ScoringRules::FULL);
+        let input = &case.input;
+        let score = input.score_fast(&case.solution).unwrap();
+        for optimizer in Optimizer::ALL {
+            let options = Options {
+                acceptance: Acceptance::Metropolis,
+                ..Default::default()
+            };
+            let result = optimize(
+                input,
+                &case.solution,
+                optimizer,
+                &options,
+                &Budget::iterations(50),
+                None,
+            );
+            // 楽器が1種類しかない問題では焼きなましの一部が使えない
+            let Ok(result) = result else { continue };
+            assert!(input.check_solution(&result).is_ok(), "{optimizer}");
+            assert!(input.score_fast(&result).unwrap() >= score, "{optimizer}");
+        }
+    }
+}
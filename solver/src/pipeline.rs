@@ -0,0 +1,537 @@
+// 初期配置から最適化までの段をつなげて、解をメモリ上で受け渡す
+// 設定は "random_exact_rect -> yamanobori 60s -> optimizer 120s -> volume_optimize" のように書く
+use crate::budget::Budget;
+use crate::checkpoint::BestOutput;
+use crate::optimize::{self, Optimizer, Options};
+use crate::problem::*;
+use crate::solve::{self, Strategy};
+use crate::stats::ProblemStats;
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Step {
+    // 新しく解を作る
+    Solve(Strategy),
+    // それまでの最良解を良くする
+    Optimize(Optimizer),
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Step::Solve(strategy) => write!(f, "{}", strategy),
+            Step::Optimize(optimizer) => write!(f, "{}", optimizer),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Stage {
+    pub step: Step,
+    // どちらもなければ、全体の残りを使う
+    pub time_limit: Option<Duration>,
+    pub max_iterations: Option<u64>,
+    // optimizerでShieldMoveも使う (optimize --shield と同じ)
+    pub shield: bool,
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.step)?;
+        if self.shield {
+            write!(f, " shield")?;
+        }
+        if let Some(t) = self.time_limit {
+            write!(f, " {}s", t.as_secs_f64())?;
+        }
+        if let Some(n) = self.max_iterations {
+            write!(f, " {}it", n)?;
+        }
+        Ok(())
+    }
+}
+
+// "<name>" の後に "60s", "2m", "5000it" で上限を付けられる。optimizerには "shield" も付けられる
+impl FromStr for Stage {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut words = s.split_whitespace();
+        let Some(name) = words.next() else { bail!("empty stage") };
+        let step = match (name.parse::<Strategy>(), name.parse::<Optimizer>()) {
+            (Ok(strategy), _) => Step::Solve(strategy),
+            (_, Ok(optimizer)) => Step::Optimize(optimizer),
+            _ => bail!(
+                "unknown stage: {name} (one of {}, {})",
+                Strategy::ALL.map(|s| s.name()).join(", "),
+                Optimizer::ALL.map(|o| o.name()).join(", ")
+            ),
+        };
+        let mut stage = Stage {
+            step,
+            time_limit: None,
+            max_iterations: None,
+            shield: false,
+        };
+        for limit in words {
+            if limit == "shield" {
+                if step != Step::Optimize(Optimizer::Optimizer) {
+                    bail!("shield only applies to {}", Optimizer::Optimizer);
+                }
+                stage.shield = true;
+                continue;
+            }
+            let seconds = |n: &str, unit: f64| -> Result<Duration> {
+                let n: f64 = n.parse().with_context(|| format!("bad limit: {limit}"))?;
+                Ok(Duration::from_secs_f64(n * unit))
+            };
+            if let Some(n) = limit.strip_suffix("it") {
+                stage.max_iterations =
+                    Some(n.parse().with_context(|| format!("bad limit: {limit}"))?);
+            } else if let Some(n) = limit.strip_suffix('s') {
+                stage.time_limit = Some(seconds(n, 1.0)?);
+            } else if let Some(n) = limit.strip_suffix('m') {
+                stage.time_limit = Some(seconds(n, 60.0)?);
+            } else {
+                bail!("bad limit: {limit} (like 60s, 2m or 5000it)");
+            }
+        }
+        Ok(stage)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pipeline {
+    pub stages: Vec<Stage>,
+}
+
+// 段は "->", "→" か改行で区切る。"#" から行末まではコメント
+impl FromStr for Pipeline {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let stages = s
+            .lines()
+            .map(|line| line.split('#').next().unwrap())
+            .flat_map(|line| line.split("->").flat_map(|s| s.split('→')))
+            .filter(|s| !s.trim().is_empty())
+            .map(|s| s.parse())
+            .collect::<Result<Vec<Stage>>>()?;
+        if stages.is_empty() {
+            bail!("pipeline has no stages");
+        }
+        Ok(Pipeline { stages })
+    }
+}
+
+impl fmt::Display for Pipeline {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let stages: Vec<_> = self.stages.iter().map(|s| s.to_string()).collect();
+        write!(f, "{}", stages.join(" -> "))
+    }
+}
+
+// 1段の結果。scoreはその段が返した解のスコアで、正しくないか段が失敗したらNone
+#[derive(Debug, Clone, Serialize)]
+pub struct StageReport {
+    pub stage: String,
+    pub score: Option<Score>,
+    pub best_score: Score,
+    pub elapsed_sec: f64,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PipelineResult {
+    pub solution: Solution,
+    pub score: Score,
+    pub stages: Vec<StageReport>,
+}
+
+// solve autoで、最初の段に使う全体の割合
+pub const AUTO_SOLVE_SHARE: f64 = 0.25;
+// 負の嗜好がこれ以上の割合なら、volume 0のmusicianを遮蔽物に使う
+pub const AUTO_SHIELD_NEGATIVE_RATIO: f64 = 0.1;
+// ハニカムで、柱に遮られる観客がこれ以上の割合なら、柱を無視するrandom_exactは使わない
+pub const AUTO_PILLAR_BLOCKED_RATIO: f64 = 0.3;
+
+impl Pipeline {
+    // 問題の特徴から初期配置と最適化の段を選ぶ。選んだ理由も返す
+    // 最初の段にはtime_limitかmax_iterationsの1/4を使い、残りを焼きなましに使う
+    pub fn auto(
+        input: &Input,
+        stats: &ProblemStats,
+        time_limit: Option<Duration>,
+        max_iterations: Option<u64>,
+    ) -> Result<(Pipeline, Vec<String>)> {
+        if !stats.fits_honeycomb() {
+            bail!(
+                "{} musicians do not fit in {} honeycomb seats",
+                stats.musicians,
+                stats.capacity.honeycomb
+            );
+        }
+        let together = input.rules.playing_together;
+        let mut reasons = vec![];
+        let shield = input.rules.volumes
+            && stats.tastes.negative_ratio >= AUTO_SHIELD_NEGATIVE_RATIO
+            && stats.tastes.attendees_with_negative > 0;
+        let strategy = if stats.fits_grid() {
+            reasons.push(format!(
+                "grid has {} seats for {} musicians",
+                stats.capacity.grid, stats.musicians
+            ));
+            if together && stats.instruments.duplicated > 0 {
+                reasons.push(format!(
+                    "{} instruments are played by more than one musician",
+                    stats.instruments.duplicated
+                ));
+                Strategy::TwoStageExactTogether
+            } else {
+                reasons.push("no playing-together bonus to gain".to_owned());
+                if shield {
+                    Strategy::Shield
+                } else {
+                    Strategy::RandomExactRect
+                }
+            }
+        } else {
+            reasons.push(format!(
+                "grid has only {} seats for {} musicians; using the honeycomb",
+                stats.capacity.grid, stats.musicians
+            ));
+            if together && stats.instruments.max_multiplicity > 1 {
+                reasons.push(format!(
+                    "up to {} musicians per instrument; clustering them",
+                    stats.instruments.max_multiplicity
+                ));
+                Strategy::Garasubo
+            } else if stats.pillars.blocked_attendee_ratio >= AUTO_PILLAR_BLOCKED_RATIO {
+                reasons.push(format!(
+                    "{} pillars block {:.0}% of attendees; random_exact ignores them, so shuffling with the real score",
+                    stats.pillars.count,
+                    stats.pillars.blocked_attendee_ratio * 100.0
+                ));
+                Strategy::Random
+            } else {
+                Strategy::RandomExact
+            }
+        };
+        if shield {
+            reasons.push(format!(
+                "{:.0}% of tastes are negative and {} attendees dislike something; using zero-volume musicians as shields",
+                stats.tastes.negative_ratio * 100.0,
+                stats.tastes.attendees_with_negative
+            ));
+        }
+
+        let mut stages = vec![Stage {
+            step: Step::Solve(strategy),
+            time_limit: time_limit.map(|t| t.mul_f64(AUTO_SOLVE_SHARE)),
+            max_iterations: max_iterations.map(|n| ((n as f64 * AUTO_SOLVE_SHARE) as u64).max(1)),
+            shield: false,
+        }];
+        // 焼きなましは楽器が2種類以上ないと動かない
+        if stats.instruments.count >= 2 {
+            stages.push(Stage {
+                step: Step::Optimize(Optimizer::Optimizer),
+                time_limit: None,
+                max_iterations: None,
+                shield,
+            });
+        } else {
+            reasons.push("only one instrument; skipping the optimizer".to_owned());
+        }
+        if input.rules.volumes {
+            reasons.push("volumes are allowed; finishing with volume_optimize".to_owned());
+            stages.push(Stage {
+                step: Step::Optimize(Optimizer::VolumeOptimize),
+                time_limit: None,
+                max_iterations: None,
+                shield: false,
+            });
+        }
+        Ok((Pipeline { stages }, reasons))
+    }
+
+    pub fn load(path: &str) -> Result<Self> {
+        let s = std::fs::read_to_string(path).with_context(|| format!("failed to read {path}"))?;
+        s.parse().with_context(|| format!("failed to parse {path}"))
+    }
+
+    // initialがなければ最初の段はsolveでなければならない
+    // 各段はそれまでの最良解から始め、最後に全体の最良解を返す
+    // 失敗した段はreportに残して、それまでの最良解のまま次の段に進む
+    pub fn run(
+        &self,
+        input: &Input,
+        initial: Option<&Solution>,
+        options: &Options,
+        budget: &Budget,
+        output: Option<&BestOutput>,
+    ) -> Result<PipelineResult> {
+        let mut best = match initial {
+            Some(solution) => {
+                if let Err(errors) = input.check_solution(solution) {
+                    bail!("invalid solution: {}", errors);
+                }
+                Some((input.score_fast(solution)?, solution.clone()))
+            }
+            None => None,
+        };
+        // 段ごとにチェックポイントを分けられないので使わない
+        let options = Options {
+            checkpoint: None,
+            resume: false,
+            ..options.clone()
+        };
+
+        let mut reports = vec![];
+        for stage in &self.stages {
+            if budget.is_cancelled() {
+                break;
+            }
+            let stage_budget = budget.stage(stage.time_limit, stage.max_iterations);
+            let result = match (stage.step, &best) {
+                (Step::Solve(strategy), _) => {
+                    solve::solve(input, strategy, options.seed, &stage_budget)
+                }
+                (Step::Optimize(optimizer), Some((_, solution))) => {
+                    let options = Options {
+                        shield: options.shield || stage.shield,
+                        ..options.clone()
+                    };
+                    optimize::optimize(input, solution, optimizer, &options, &stage_budget, output)
+                }
+                (Step::Optimize(optimizer), None) => {
+                    bail!("{optimizer} needs a solution; start the pipeline with a strategy")
+                }
+            };
+            let (result, error) = match result {
+                Ok(result) => (Some(result), None),
+                Err(e) => {
+                    eprintln!("{} failed: {:#}", stage.step, e);
+                    (None, Some(format!("{:#}", e)))
+                }
+            };
+            let score = match &result {
+                Some(result) => match input.check_solution(result) {
+                    Ok(()) => Some(input.score_fast(result)?),
+                    Err(errors) => {
+                        eprintln!("{} made an invalid solution: {}", stage.step, errors);
+                        None
+                    }
+                },
+                None => None,
+            };
+            if let (Some(score), Some(result)) = (score, result) {
+                if best
+                    .as_ref()
+                    .map_or(true, |(best_score, _)| score > *best_score)
+                {
+                    if let Some(output) = output {
+                        output.offer(&result)?;
+                    }
+                    best = Some((score, result));
+                }
+            }
+            let report = StageReport {
+                stage: stage.to_string(),
+                score,
+                best_score: best.as_ref().map_or(Score::MIN, |(score, _)| *score),
+                elapsed_sec: stage_budget.elapsed().as_secs_f64(),
+                error,
+            };
+            eprintln!(
+                "stage {}: score {:?}, best {}, {:.1}s",
+                report.stage, report.score, report.best_score, report.elapsed_sec
+            );
+            reports.push(report);
+        }
+
+        let Some((score, solution)) = best else { bail!("no stage made a valid solution") };
+        Ok(PipelineResult {
+            solution,
+            score,
+            stages: reports,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cross_check::random_case;
+    use crate::problem::ScoringRules;
+    use geo::Point;
+    use rand_pcg::Pcg64Mcg;
+
+    #[test]
+    fn parse_pipeline() {
+        let pipeline: Pipeline =
+            "random_exact_rect → yamanobori 60s -> optimizer shield 2m 5000it\n\
+                                  # 最後にvolumeを決める\n\
+                                  volume_optimize"
+                .parse()
+                .unwrap();
+        assert_eq!(
+            pipeline.stages,
+            vec![
+                Stage {
+                    step: Step::Solve(Strategy::RandomExactRect),
+                    time_limit: None,
+                    max_iterations: None,
+                    shield: false,
+                },
+                Stage {
+                    step: Step::Optimize(Optimizer::Yamanobori),
+                    time_limit: Some(Duration::from_secs(60)),
+                    max_iterations: None,
+                    shield: false,
+                },
+                Stage {
+                    step: Step::Optimize(Optimizer::Optimizer),
+                    time_limit: Some(Duration::from_secs(120)),
+                    max_iterations: Some(5000),
+                    shield: true,
+                },
+                Stage {
+                    step: Step::Optimize(Optimizer::VolumeOptimize),
+                    time_limit: None,
+                    max_iterations: None,
+                    shield: false,
+                },
+            ]
+        );
+        assert_eq!(pipeline.to_string().parse::<Pipeline>().unwrap(), pipeline);
+        assert!("".parse::<Pipeline>().is_err());
+        assert!("random -> sa".parse::<Pipeline>().is_err());
+        assert!("random 10x".parse::<Pipeline>().is_err());
+        assert!("yamanobori shield".parse::<Pipeline>().is_err());
+    }
+
+    #[test]
+    fn auto_pipeline() {
+        let auto = |input: &Input| {
+            let stats = ProblemStats::new(None, input);
+            let (pipeline, reasons) =
+                Pipeline::auto(input, &stats, Some(Duration::from_secs(40)), None).unwrap();
+            (pipeline.to_string(), reasons)
+        };
+        let input = crate::read_input("./testdata/problem-80.json", None).unwrap();
+        let stats = ProblemStats::new(Some(80), &input);
+        let (pipeline, reasons) = Pipeline::auto(&input, &stats, None, Some(100)).unwrap();
+        // 格子に並び、同じ楽器が複数あり、fullのルールで、嫌われる楽器も多い
+        assert_eq!(
+            pipeline.to_string(),
+            "two_stage_exact_together 25it -> optimizer shield -> volume_optimize"
+        );
+        assert!(reasons.len() >= 4);
+
+        let mut input = crate::read_input("./testdata/problem-29.json", None).unwrap();
+        assert!(ProblemStats::new(Some(29), &input).fits_grid());
+        input.rules = ScoringRules::LIGHTNING;
+        assert_eq!(auto(&input).0, "random_exact_rect 10s -> optimizer");
+
+        // 嗜好の1/3ほどが負なので、volumeが使えれば遮蔽物を置く
+        input.rules.volumes = true;
+        assert_eq!(
+            auto(&input).0,
+            "shield 10s -> optimizer shield -> volume_optimize"
+        );
+        let mut liked = input.clone();
+        for attendee in &mut liked.attendees {
+            attendee.tastes.iter_mut().for_each(|t| *t = t.abs());
+        }
+        assert_eq!(
+            auto(&liked).0,
+            "random_exact_rect 10s -> optimizer -> volume_optimize"
+        );
+
+        // 楽器が1種類なら焼きなましは使わない
+        input.rules = ScoringRules::LIGHTNING;
+        let stats = ProblemStats::new(Some(29), &input);
+        input.musicians = vec![0; input.musicians.len()];
+        assert_eq!(auto(&input).0, "random_exact_rect 10s");
+
+        // 格子に入りきらなければハニカム。柱で視線が遮られる観客が多ければrandom_exactは使わない
+        input.musicians = (0..=stats.capacity.grid).map(|i| i % 2).collect();
+        assert_eq!(auto(&input).0, "random_exact 10s -> optimizer");
+        let center = Point::new(
+            input.stage_bottom_left.x() + input.stage_width / 2.0,
+            input.stage_bottom_left.y() + input.stage_height / 2.0,
+        );
+        input.pillars = input
+            .attendees
+            .iter()
+            .map(|a| Pillar {
+                center: Point::new((a.x + center.x()) / 2.0, (a.y + center.y()) / 2.0),
+                radius: 1.0,
+            })
+            .collect();
+        input.rules.pillars = true;
+        let (pipeline, reasons) = auto(&input);
+        assert_eq!(pipeline, "random 10s -> optimizer");
+        assert!(reasons.iter().any(|r| r.contains("pillars")));
+
+        input.musicians = vec![0; stats.capacity.honeycomb + 1];
+        let stats = ProblemStats::new(Some(29), &input);
+        assert!(Pipeline::auto(&input, &stats, None, Some(1)).is_err());
+    }
+
+    #[test]
+    fn run_pipeline() {
+        let mut rng = Pcg64Mcg::new(3);
+        let case = random_case(&mut rng, ScoringRules::FULL);
+        let input = &case.input;
+        let pipeline: Pipeline = "random2 2it -> yamanobori 20it -> volume_optimize"
+            .parse()
+            .unwrap();
+        let result = pipeline
+            .run(
+                input,
+                Some(&case.solution),
+                &Options::default(),
+                &Budget::unlimited(),
+                None,
+            )
+            .unwrap();
+        assert_eq!(result.stages.len(), 3);
+        assert!(input.check_solution(&result.solution).is_ok());
+        assert_eq!(input.score_fast(&result.solution).unwrap(), result.score);
+        // 最良解は段を進めても悪くならない
+        for w in result.stages.windows(2) {
+            assert!(w[0].best_score <= w[1].best_score);
+        }
+        assert_eq!(result.stages.last().unwrap().best_score, result.score);
+        assert!(result.score >= input.score_fast(&case.solution).unwrap());
+
+        // 楽器が1種類だと焼きなましは失敗するが、最良解はそのまま残る
+        let mut single = case.clone();
+        single.input.musicians = vec![0; single.input.musicians.len()];
+        let failing: Pipeline = "optimizer 10it -> yamanobori 5it".parse().unwrap();
+        let result = failing
+            .run(
+                &single.input,
+                Some(&single.solution),
+                &Options::default(),
+                &Budget::unlimited(),
+                None,
+            )
+            .unwrap();
+        assert_eq!(result.stages.len(), 2);
+        assert_eq!(result.stages[0].score, None);
+        assert!(result.stages[0].error.is_some());
+        assert!(result.stages[1].error.is_none());
+        assert!(result.score >= single.input.score_fast(&single.solution).unwrap());
+
+        let optimize_first: Pipeline = "optimizer 10it".parse().unwrap();
+        assert!(optimize_first
+            .run(input, None, &Options::default(), &Budget::unlimited(), None)
+            .is_err());
+    }
+}
@@ -1,3 +1,4 @@
+use crate::spatial::BlockerGrid;
 use anyhow::bail;
 use anyhow::Result;
 use geo::EuclideanDistance;
@@ -9,6 +10,8 @@ use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, VecDeque};
 use std::f64::consts::PI;
+use std::fmt;
+use std::str::FromStr;
 
 #[derive(Debug, Clone, Copy)]
 pub struct Segment {
@@ -41,6 +44,13 @@ pub struct Attendee {
 
 type MusicianId = usize;
 type AttendeeId = usize;
+// 公式と同じく各項をceilした整数で持つ。f64は表示や焼きなましの温度計算用
+pub type Score = i128;
+
+// ceil(volume * closeness * raw_impact)。公式の丸めと同じ順で計算する
+pub fn impact_score(volume: f64, closeness: f64, raw_impact: f64) -> Score {
+    (volume * closeness * raw_impact).ceil() as Score
+}
 
 impl Attendee {
     pub fn pos(&self) -> Point {
@@ -48,7 +58,9 @@ impl Attendee {
     }
 }
 
-const BLOCKED_DIST: f64 = 5.0;
+pub(crate) const BLOCKED_DIST: f64 = 5.0;
+// 角度スイープでこれより境界に近いものは線分判定で決める
+const ANGLE_EPS: f64 = 1e-9;
 
 #[derive(Debug, Copy, Clone)]
 struct AngleInfo {
@@ -68,7 +80,7 @@ impl AngleInfo {
 pub fn filter_placements_blocked_by_pillars(
     attendee_pos: Point,
     placements: &[Point],
-    pillars: &Vec<Pillar>,
+    pillars: &[Pillar],
     prefiltered_ids: &Vec<usize>,
 ) -> Vec<usize> {
     let mut result = vec![];
@@ -80,7 +92,7 @@ pub fn filter_placements_blocked_by_pillars(
             p2: placements[id],
         };
         for pillar in pillars {
-            if segment.dist(&pillar.center) <= pillar.radius {
+            if segment.dist(&pillar.center) < pillar.radius {
                 is_blocked = true;
                 break;
             }
@@ -93,12 +105,12 @@ pub fn filter_placements_blocked_by_pillars(
     result
 }
 
-pub fn get_non_blocked_placement_ids(attendee_pos: Point, placements: &Vec<Point>) -> Vec<usize> {
+pub fn get_non_blocked_placement_ids(attendee_pos: Point, placements: &[Point]) -> Vec<usize> {
     // Compute nearest musician
     let mut nearest_place_id = 0;
     let mut nearest_distance = OrderedFloat(attendee_pos.euclidean_distance(&placements[0]));
-    for placement_id in 1..placements.len() {
-        let distance = OrderedFloat(attendee_pos.euclidean_distance(&placements[placement_id]));
+    for (placement_id, placement) in placements.iter().enumerate().skip(1) {
+        let distance = OrderedFloat(attendee_pos.euclidean_distance(placement));
         if distance < nearest_distance {
             nearest_place_id = placement_id;
             nearest_distance = distance
@@ -107,9 +119,9 @@ pub fn get_non_blocked_placement_ids(attendee_pos: Point, placements: &Vec<Point
 
     // Compute relative angles for each musician based on
     let mut angles = vec![];
-    for placement_id in 0..placements.len() {
-        let dx: f64 = placements[placement_id].x() - attendee_pos.x();
-        let dy: f64 = placements[placement_id].y() - attendee_pos.y();
+    for (placement_id, placement) in placements.iter().enumerate() {
+        let dx: f64 = placement.x() - attendee_pos.x();
+        let dy: f64 = placement.y() - attendee_pos.y();
         let angle = dy.atan2(dx);
         let dist_sq = dx * dx + dy * dy;
         // angles.push((OrderedFloat(angle), dist_sq, musician_id));
@@ -122,12 +134,12 @@ pub fn get_non_blocked_placement_ids(attendee_pos: Point, placements: &Vec<Point
     }
 
     let nearest_place_angle = angles[nearest_place_id].angle;
-    for placement_id in 0..placements.len() {
-        if angles[placement_id].angle >= nearest_place_angle {
-            angles[placement_id].angle -= nearest_place_angle;
+    for angle_info in angles.iter_mut() {
+        if angle_info.angle >= nearest_place_angle {
+            angle_info.angle -= nearest_place_angle;
         } else {
-            angles[placement_id].angle += 2.0 * PI;
-            angles[placement_id].angle -= nearest_place_angle;
+            angle_info.angle += 2.0 * PI;
+            angle_info.angle -= nearest_place_angle;
         }
     }
     angles.sort_by_key(|&angle_info| (angle_info.angle, angle_info.dist_sq));
@@ -138,6 +150,8 @@ pub fn get_non_blocked_placement_ids(attendee_pos: Point, placements: &Vec<Point
     angles.push(last_element);
 
     let mut is_blocked = vec![false; angles.len()];
+    // 接線に近く、角度の比較では誤差で判定がぶれるもの
+    let mut is_ambiguous = vec![false; angles.len()];
 
     // a(i): musician i's angle from the attendee
     // d(i): musician i's distance from the attendee
@@ -167,8 +181,10 @@ pub fn get_non_blocked_placement_ids(attendee_pos: Point, placements: &Vec<Point
         }
 
         let max_end_angle = max_end_angle_stack.back().unwrap().1;
-        if max_end_angle > angle_info.angle {
+        if max_end_angle > angle_info.angle + ANGLE_EPS {
             is_blocked[i] = true;
+        } else if max_end_angle > angle_info.angle - ANGLE_EPS {
+            is_ambiguous[i] = true;
         }
 
         let (_, new_end_angle) = angle_info.get_covered_angle_range();
@@ -197,8 +213,10 @@ pub fn get_non_blocked_placement_ids(attendee_pos: Point, placements: &Vec<Point
         }
 
         let min_start_angle = min_start_angle_stack.back().unwrap().1;
-        if min_start_angle < angle_info.angle {
+        if min_start_angle < angle_info.angle - ANGLE_EPS {
             is_blocked[i] = true;
+        } else if min_start_angle < angle_info.angle + ANGLE_EPS {
+            is_ambiguous[i] = true;
         }
 
         let (new_start_angle, _) = angle_info.get_covered_angle_range();
@@ -209,26 +227,307 @@ pub fn get_non_blocked_placement_ids(attendee_pos: Point, placements: &Vec<Point
 
     let mut non_blocke_placement_ids = vec![];
     for i in 0..angles.len() - 1 {
-        if !is_blocked[i] {
-            let placement_id = angles[i].placement_id;
-            non_blocke_placement_ids.push(placement_id);
+        if is_blocked[i] {
+            continue;
+        }
+        let placement_id = angles[i].placement_id;
+        if is_ambiguous[i] {
+            let segment = Segment {
+                p1: attendee_pos,
+                p2: placements[placement_id],
+            };
+            let blocked = placements
+                .iter()
+                .enumerate()
+                .any(|(j, p)| j != placement_id && segment.dist(p) < BLOCKED_DIST);
+            if blocked {
+                continue;
+            }
         }
+        non_blocke_placement_ids.push(placement_id);
     }
     non_blocke_placement_ids
 }
 
+// attendeeとmusicianを結ぶ線分を遮っているもの
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(tag = "kind", content = "id", rename_all = "snake_case")]
+pub enum Blocker {
+    Musician(MusicianId),
+    Pillar(usize),
+}
+
+// attendee, musicianの組ごとのスコアの内訳
+#[derive(Debug, Serialize, Clone)]
+pub struct ImpactDetail {
+    pub musician_id: MusicianId,
+    pub raw_impact: f64,
+    pub blocked_by: Option<Blocker>,
+    pub volume: f64,
+    pub closeness: f64,
+    // ceil(volume * closeness * raw_impact)、遮られていれば0
+    pub score: Score,
+}
+
+#[derive(Debug, Serialize, Clone)]
 pub struct AttendeeScoreDetail {
     pub attendee_id: usize,
     pub matched_musician_ids: Vec<usize>,
-    pub score: f64,
+    pub score: Score,
+    pub impacts: Vec<ImpactDetail>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct MusicianScoreDetail {
+    pub musician_id: MusicianId,
+    pub instrument: usize,
+    pub volume: f64,
+    pub closeness: f64,
+    pub score: Score,
+    pub visible_attendees: usize,
+    pub blocked_by_musicians: usize,
+    pub blocked_by_pillars: usize,
+    // 遮られていなければ得られたスコア
+    pub blocked_score: Score,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ScoreBreakdown {
+    pub score: Score,
+    pub attendees: Vec<AttendeeScoreDetail>,
+    pub musicians: Vec<MusicianScoreDetail>,
+}
+
+// 解の制約違反
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ValidationError {
+    WrongCount {
+        expected: usize,
+        actual: usize,
+    },
+    NanCoordinates {
+        musician: MusicianId,
+        x: f64,
+        y: f64,
+    },
+    OutsideStage {
+        musician: MusicianId,
+        x: f64,
+        y: f64,
+    },
+    TooCloseToWall {
+        musician: MusicianId,
+        x: f64,
+        y: f64,
+    },
+    MusiciansTooClose {
+        musicians: (MusicianId, MusicianId),
+        dist: f64,
+    },
+    WrongVolumeCount {
+        expected: usize,
+        actual: usize,
+    },
+    BadVolume {
+        musician: MusicianId,
+        volume: f64,
+    },
 }
 
+impl ValidationError {
+    // 違反に関係するmusicianのID
+    pub fn musicians(&self) -> Vec<MusicianId> {
+        match *self {
+            ValidationError::WrongCount { .. } | ValidationError::WrongVolumeCount { .. } => {
+                vec![]
+            }
+            ValidationError::NanCoordinates { musician, .. }
+            | ValidationError::OutsideStage { musician, .. }
+            | ValidationError::TooCloseToWall { musician, .. }
+            | ValidationError::BadVolume { musician, .. } => vec![musician],
+            ValidationError::MusiciansTooClose {
+                musicians: (i, j), ..
+            } => vec![i, j],
+        }
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationError::WrongCount { expected, actual } => write!(
+                f,
+                "placements.len() != musicians.len(): {} != {}",
+                actual, expected
+            ),
+            ValidationError::NanCoordinates { musician, x, y } => {
+                write!(
+                    f,
+                    "musician {} has non-finite coordinates: ({}, {})",
+                    musician, x, y
+                )
+            }
+            ValidationError::OutsideStage { musician, x, y } => {
+                write!(
+                    f,
+                    "{}-th musician is not in stage: ({}, {})",
+                    musician, x, y
+                )
+            }
+            ValidationError::TooCloseToWall { musician, x, y } => write!(
+                f,
+                "musician {} is too close to room walls: ({}, {})",
+                musician, x, y
+            ),
+            ValidationError::MusiciansTooClose {
+                musicians: (i, j),
+                dist,
+            } => write!(f, "musicians {} and {} are too close: dist={}", i, j, dist),
+            ValidationError::WrongVolumeCount { expected, actual } => write!(
+                f,
+                "volumes.len() != musicians.len(): {} != {}",
+                actual, expected
+            ),
+            ValidationError::BadVolume { musician, volume } => write!(
+                f,
+                "musician {} has volume {} outside of 0..={}",
+                musician, volume, MAX_VOLUME
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct ValidationErrors(pub Vec<ValidationError>);
+
+impl ValidationErrors {
+    // 違反に関係するmusicianのID (重複なし、昇順)
+    pub fn musicians(&self) -> Vec<MusicianId> {
+        let mut ids = self
+            .0
+            .iter()
+            .flat_map(|e| e.musicians())
+            .collect::<Vec<_>>();
+        ids.sort();
+        ids.dedup();
+        ids
+    }
+}
+
+impl fmt::Display for ValidationErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} violation(s)", self.0.len())?;
+        for e in self.0.iter() {
+            write!(f, "\n  {}", e)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ValidationErrors {}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Pillar {
     pub center: Point,
     pub radius: f64,
 }
 
+pub const LAST_LIGHTNING_PROBLEM_ID: i32 = 55;
+pub const DEFAULT_VOLUME: f64 = 1.0;
+pub const MAX_VOLUME: f64 = 10.0;
+
+// 採点時に有効な拡張ルール
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub struct ScoringRules {
+    pub pillars: bool,
+    pub playing_together: bool,
+    pub volumes: bool,
+}
+
+impl ScoringRules {
+    pub const LIGHTNING: ScoringRules = ScoringRules {
+        pillars: false,
+        playing_together: false,
+        volumes: false,
+    };
+
+    pub const FULL: ScoringRules = ScoringRules {
+        pillars: true,
+        playing_together: true,
+        volumes: true,
+    };
+
+    // 公式の採点ルール: volumeは全問題、pillarとplaying togetherはfull roundの問題のみ
+    pub fn official(is_full: bool) -> Self {
+        if is_full {
+            ScoringRules::FULL
+        } else {
+            ScoringRules {
+                volumes: true,
+                ..ScoringRules::LIGHTNING
+            }
+        }
+    }
+
+    pub fn for_problem_id(id: i32) -> Self {
+        ScoringRules::official(id > LAST_LIGHTNING_PROBLEM_ID)
+    }
+}
+
+impl Default for ScoringRules {
+    fn default() -> Self {
+        ScoringRules::FULL
+    }
+}
+
+// "lightning", "full" または "pillars,playing_together,volumes" のような拡張の列挙
+impl FromStr for ScoringRules {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "lightning" => return Ok(ScoringRules::LIGHTNING),
+            "full" => return Ok(ScoringRules::FULL),
+            _ => {}
+        }
+        let mut rules = ScoringRules::LIGHTNING;
+        for ext in s.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            match ext {
+                "pillars" => rules.pillars = true,
+                "playing_together" | "together" => rules.playing_together = true,
+                "volumes" => rules.volumes = true,
+                _ => bail!("unknown scoring extension: {ext}"),
+            }
+        }
+        Ok(rules)
+    }
+}
+
+impl fmt::Display for ScoringRules {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            ScoringRules::LIGHTNING => write!(f, "lightning"),
+            ScoringRules::FULL => write!(f, "full"),
+            _ => {
+                let exts = [
+                    (self.pillars, "pillars"),
+                    (self.playing_together, "playing_together"),
+                    (self.volumes, "volumes"),
+                ];
+                let names = exts
+                    .iter()
+                    .filter(|(on, _)| *on)
+                    .map(|(_, name)| *name)
+                    .collect::<Vec<_>>();
+                write!(f, "{}", names.join(","))
+            }
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Input {
     pub room_width: f64,
@@ -239,9 +538,30 @@ pub struct Input {
     pub musicians: Vec<usize>,
     pub attendees: Vec<Attendee>,
     pub pillars: Vec<Pillar>,
+    // 問題JSONには含まれないので読み込み後に設定する (solver::read_input を参照)
+    #[serde(skip)]
+    pub rules: ScoringRules,
 }
 
 impl Input {
+    // 採点ルール上で視線を遮るpillar
+    pub fn active_pillars(&self) -> &[Pillar] {
+        if self.rules.pillars {
+            &self.pillars
+        } else {
+            &[]
+        }
+    }
+
+    // 各musicianのvolume。volume拡張が無効なら常に1.0
+    pub fn volume(&self, solution: &Solution, musician_id: MusicianId) -> f64 {
+        if self.rules.volumes {
+            solution.volume(musician_id)
+        } else {
+            DEFAULT_VOLUME
+        }
+    }
+
     pub fn in_stage(&self, p: &Point) -> bool {
         const MUSICIAN_CLOSE_DIST: f64 = 10.0;
         p.x() >= self.stage_bottom_left.x() + MUSICIAN_CLOSE_DIST
@@ -250,56 +570,140 @@ impl Input {
             && p.y() <= self.stage_bottom_left.y() + self.stage_height - MUSICIAN_CLOSE_DIST
     }
 
-    pub fn is_valid_placements(&self, placements: &Vec<Point>) -> Result<()> {
-        if placements.len() != self.musicians.len() {
-            bail!(
-                "placements.len() != musicians.len(): {} != {}",
-                placements.len(),
-                self.musicians.len(),
-            );
+    // 最初に見つかった違反をエラーとして返す
+    pub fn is_valid_placements(&self, placements: &[Point]) -> Result<()> {
+        match self.collect_violations(placements, None, true).pop() {
+            Some(e) => Err(e.into()),
+            None => Ok(()),
         }
+    }
 
-        // Check musicians are in stage
-        for i in 0..self.musicians.len() {
-            if !self.in_stage(&placements[i]) {
-                bail!("{i}-th musician is not in stage: {:?}", placements[i]);
-            }
+    // movesの通りにmusicianを動かしても正しい配置のままか
+    // 動かすmusicianについてだけ調べるので、元の配置は正しいものとする
+    pub fn is_valid_relocation(&self, placements: &[Point], moves: &[(MusicianId, Point)]) -> bool {
+        const MUSICIAN_CLOSE_DIST: f64 = 10.0;
+        let position = |i: usize| {
+            moves
+                .iter()
+                .rev()
+                .find(|(m, _)| *m == i)
+                .map_or(placements[i], |&(_, p)| p)
+        };
+        moves.iter().all(|&(m, p)| {
+            p.x().is_finite()
+                && p.y().is_finite()
+                && self.in_stage(&p)
+                && (MUSICIAN_CLOSE_DIST <= p.x() && p.x() <= self.room_width - MUSICIAN_CLOSE_DIST)
+                && (MUSICIAN_CLOSE_DIST <= p.y() && p.y() <= self.room_height - MUSICIAN_CLOSE_DIST)
+                && (0..placements.len())
+                    .all(|j| j == m || position(j).euclidean_distance(&p) >= MUSICIAN_CLOSE_DIST)
+        })
+    }
+
+    // 配置の違反をすべて列挙する
+    pub fn placement_violations(&self, placements: &[Point]) -> Vec<ValidationError> {
+        self.collect_violations(placements, None, false)
+    }
+
+    // 配置とvolumeの違反をすべて列挙し、あればエラーにする
+    pub fn check_solution(&self, solution: &Solution) -> std::result::Result<(), ValidationErrors> {
+        let errors =
+            self.collect_violations(&solution.placements, solution.volumes.as_deref(), false);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ValidationErrors(errors))
+        }
+    }
+
+    fn collect_violations(
+        &self,
+        placements: &[Point],
+        volumes: Option<&[f64]>,
+        stop_at_first: bool,
+    ) -> Vec<ValidationError> {
+        let mut errors = vec![];
+        macro_rules! report {
+            ($e:expr) => {
+                errors.push($e);
+                if stop_at_first {
+                    return errors;
+                }
+            };
+        }
+
+        if placements.len() != self.musicians.len() {
+            report!(ValidationError::WrongCount {
+                expected: self.musicians.len(),
+                actual: placements.len(),
+            });
         }
 
-        // Check distance from room walls
         const MUSICIAN_CLOSE_DIST: f64 = 10.0;
-        for i in 0..self.musicians.len() {
-            let pos = placements[i];
+        for (i, &pos) in placements.iter().enumerate() {
+            if !pos.x().is_finite() || !pos.y().is_finite() {
+                report!(ValidationError::NanCoordinates {
+                    musician: i,
+                    x: pos.x(),
+                    y: pos.y(),
+                });
+                continue;
+            }
+
+            // Check musicians are in stage
+            if !self.in_stage(&pos) {
+                report!(ValidationError::OutsideStage {
+                    musician: i,
+                    x: pos.x(),
+                    y: pos.y(),
+                });
+            }
+
+            // Check distance from room walls
             if !((MUSICIAN_CLOSE_DIST <= pos.x()
                 && pos.x() <= self.room_width - MUSICIAN_CLOSE_DIST)
                 && (MUSICIAN_CLOSE_DIST <= pos.y()
                     && pos.y() <= self.room_height - MUSICIAN_CLOSE_DIST))
             {
-                bail!(
-                    "musician {} is too close to room walls: {:?}",
-                    i,
-                    placements[i],
-                );
+                report!(ValidationError::TooCloseToWall {
+                    musician: i,
+                    x: pos.x(),
+                    y: pos.y(),
+                });
             }
         }
 
         // Check ditances between musicians
-        for i in 0..(self.musicians.len() - 1) {
-            for j in (i + 1)..self.musicians.len() {
+        for i in 0..placements.len() {
+            for j in (i + 1)..placements.len() {
                 let dist = placements[i].euclidean_distance(&placements[j]);
                 if dist < MUSICIAN_CLOSE_DIST {
-                    bail!(
-                        "musicians {} and {} are too close: {:?} {:?}: dist={dist}",
-                        i,
-                        j,
-                        placements[i],
-                        placements[j],
-                    );
+                    report!(ValidationError::MusiciansTooClose {
+                        musicians: (i, j),
+                        dist,
+                    });
                 }
             }
         }
 
-        Ok(())
+        if let Some(volumes) = volumes {
+            if volumes.len() != self.musicians.len() {
+                report!(ValidationError::WrongVolumeCount {
+                    expected: self.musicians.len(),
+                    actual: volumes.len(),
+                });
+            }
+            for (i, &volume) in volumes.iter().enumerate() {
+                if !(0.0..=MAX_VOLUME).contains(&volume) {
+                    report!(ValidationError::BadVolume {
+                        musician: i,
+                        volume,
+                    });
+                }
+            }
+        }
+
+        errors
     }
 
     pub fn raw_impact_for_instrument(
@@ -338,59 +742,63 @@ impl Input {
             );
         }
 
+        if self
+            .find_blocker(attendee_id, musician_id, placements)
+            .is_some()
+        {
+            return Ok(0.0);
+        }
+
+        Ok(self.raw_impact(attendee_id, musician_id, &placements[musician_id]))
+    }
+
+    // attendeeからmusicianへの視線を遮る最初のpillarまたはmusicianを返す
+    pub fn find_blocker(
+        &self,
+        attendee_id: AttendeeId,
+        musician_id: MusicianId,
+        placements: &[Point],
+    ) -> Option<Blocker> {
         let a_pos = self.attendees[attendee_id].pos();
         let segment = Segment {
             p1: a_pos,
             p2: placements[musician_id],
         };
-        for p in self.pillars.iter() {
+        for (i, p) in self.active_pillars().iter().enumerate() {
             if segment.dist(&p.center) < p.radius {
-                return Ok(0.0);
+                return Some(Blocker::Pillar(i));
             }
         }
-        for i in 0..placements.len() {
+        for (i, p) in placements.iter().enumerate() {
             if i == musician_id {
                 continue;
             }
 
-            if segment.dist(&placements[i]) < BLOCKED_DIST {
-                return Ok(0.0);
+            if segment.dist(p) < BLOCKED_DIST {
+                return Some(Blocker::Musician(i));
             }
         }
+        None
+    }
 
-        Ok(self.raw_impact(attendee_id, musician_id, &placements[musician_id]))
+    // 配置ごとに一度作って遮蔽判定に使い回す
+    pub fn blocker_grid(&self, placements: &[Point]) -> BlockerGrid {
+        BlockerGrid::new(placements, self.active_pillars())
     }
 
     // ある地点から見える参加者のIDを返す
     pub fn get_visible_attendees(&self, point: Point, placements: &[Point]) -> Vec<usize> {
-        let mut result = Vec::new();
-        for (i, attendee) in self.attendees.iter().enumerate() {
-            let segment = Segment {
-                p1: point,
-                p2: attendee.pos(),
-            };
-            let mut blocked = false;
-            for p in self.pillars.iter() {
-                if segment.dist(&p.center) < p.radius {
-                    blocked = true;
-                    break;
-                }
-            }
-            if blocked {
-                continue;
-            }
-            for p in placements.iter() {
-                if segment.dist(p) < BLOCKED_DIST {
-                    blocked = true;
-                    break;
-                }
-            }
-            if !blocked {
-                result.push(i);
-            }
-        }
+        self.get_visible_attendees_with(&self.blocker_grid(placements), point)
+    }
 
-        result
+    // 同じ配置で何度も調べるときはgridを使い回す
+    pub fn get_visible_attendees_with(&self, grid: &BlockerGrid, point: Point) -> Vec<usize> {
+        self.attendees
+            .iter()
+            .enumerate()
+            .filter(|(_, attendee)| !grid.is_blocked(point, attendee.pos(), None))
+            .map(|(i, _)| i)
+            .collect()
     }
 
     // 特定参加者から得られる特定地点で楽器を演奏した場合のスコア
@@ -409,30 +817,40 @@ impl Input {
 
     // 特定のmusiciansが得られるスコアを計算する
     // play together, volumeの影響は考慮しない
-    pub fn raw_score_for_musician(&self, musician_id: MusicianId, placements: &Vec<Point>) -> f64 {
+    pub fn raw_score_for_musician(&self, musician_id: MusicianId, placements: &[Point]) -> f64 {
         let placement = placements[musician_id];
-        let mut placements = placements.clone();
+        let mut placements = placements.to_vec();
         placements.remove(musician_id);
         let attendee_ids = self.get_visible_attendees(placement, &placements);
         self.raw_score_for_instrument(placement, self.musicians[musician_id], &attendee_ids)
     }
 
     #[cfg(not(target_arch = "wasm32"))]
-    pub fn score(&self, placements: &Vec<Point>) -> Result<f64> {
-        let full_div = !self.pillars.is_empty();
-        let impacts = if full_div {
-            self.calc_playing_together(placements)
-        } else {
-            vec![1.0; self.musicians.len()]
-        };
+    pub fn score(&self, placements: &Vec<Point>) -> Result<Score> {
+        if placements.len() != self.musicians.len() {
+            bail!(
+                "placements.len() != musicians.len(): {} != {}",
+                placements.len(),
+                self.musicians.len(),
+            );
+        }
+        let impacts = self.playing_together_factors(placements);
+        let grid = self.blocker_grid(placements);
         let ans = (0..self.attendees.len())
             .into_par_iter()
             .map(|attendee_id| {
-                let mut sum_impact = 0.0;
-                for musician_id in 0..self.musicians.len() {
-                    sum_impact += (impacts[musician_id]
-                        * self.impact(attendee_id, musician_id, placements).unwrap())
-                    .ceil();
+                let a_pos = self.attendees[attendee_id].pos();
+                let mut sum_impact = 0;
+                for (musician_id, &factor) in impacts.iter().enumerate() {
+                    let pos = placements[musician_id];
+                    if grid.is_blocked(a_pos, pos, Some(musician_id)) {
+                        continue;
+                    }
+                    sum_impact += impact_score(
+                        DEFAULT_VOLUME,
+                        factor,
+                        self.raw_impact(attendee_id, musician_id, &pos),
+                    );
                 }
                 sum_impact
             })
@@ -441,15 +859,105 @@ impl Input {
         Ok(ans)
     }
 
+    pub fn attendee_score_detail(
+        &self,
+        attendee_id: AttendeeId,
+        solution: &Solution,
+        closeness: &[f64],
+        grid: &BlockerGrid,
+    ) -> AttendeeScoreDetail {
+        let placements = &solution.placements;
+        let a_pos = self.attendees[attendee_id].pos();
+        let mut impacts = Vec::with_capacity(self.musicians.len());
+        let mut matched_musician_ids = vec![];
+        let mut score = 0;
+        for (musician_id, &factor) in closeness.iter().enumerate() {
+            let raw_impact = self.raw_impact(attendee_id, musician_id, &placements[musician_id]);
+            let blocked_by = grid.find_blocker(a_pos, placements[musician_id], Some(musician_id));
+            let volume = self.volume(solution, musician_id);
+            let term = if blocked_by.is_some() {
+                0
+            } else {
+                matched_musician_ids.push(musician_id);
+                impact_score(volume, factor, raw_impact)
+            };
+            score += term;
+            impacts.push(ImpactDetail {
+                musician_id,
+                raw_impact,
+                blocked_by,
+                volume,
+                closeness: factor,
+                score: term,
+            });
+        }
+        AttendeeScoreDetail {
+            attendee_id,
+            matched_musician_ids,
+            score,
+            impacts,
+        }
+    }
+
+    // 全attendee, musicianについてスコアの内訳を計算する
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn score_breakdown(&self, solution: &Solution) -> Result<ScoreBreakdown> {
+        self.check_solution(solution)?;
+        let closeness = self.playing_together_factors(&solution.placements);
+        let grid = self.blocker_grid(&solution.placements);
+        let attendees: Vec<AttendeeScoreDetail> = (0..self.attendees.len())
+            .into_par_iter()
+            .map(|attendee_id| self.attendee_score_detail(attendee_id, solution, &closeness, &grid))
+            .collect();
+
+        let mut musicians: Vec<MusicianScoreDetail> = (0..self.musicians.len())
+            .map(|musician_id| MusicianScoreDetail {
+                musician_id,
+                instrument: self.musicians[musician_id],
+                volume: self.volume(solution, musician_id),
+                closeness: closeness[musician_id],
+                score: 0,
+                visible_attendees: 0,
+                blocked_by_musicians: 0,
+                blocked_by_pillars: 0,
+                blocked_score: 0,
+            })
+            .collect();
+        for attendee in attendees.iter() {
+            for impact in attendee.impacts.iter() {
+                let m = &mut musicians[impact.musician_id];
+                match impact.blocked_by {
+                    None => {
+                        m.visible_attendees += 1;
+                        m.score += impact.score;
+                    }
+                    Some(blocker) => {
+                        match blocker {
+                            Blocker::Musician(_) => m.blocked_by_musicians += 1,
+                            Blocker::Pillar(_) => m.blocked_by_pillars += 1,
+                        }
+                        m.blocked_score +=
+                            impact_score(impact.volume, impact.closeness, impact.raw_impact);
+                    }
+                }
+            }
+        }
+
+        Ok(ScoreBreakdown {
+            score: attendees.iter().map(|a| a.score).sum(),
+            attendees,
+            musicians,
+        })
+    }
+
     pub fn score_attendee_fast(
         &self,
         attendee_id: usize,
         solution: &Solution,
         impacts: &[f64],
-    ) -> f64 {
-        let mut sum_impact = 0.0;
+    ) -> Score {
+        let mut sum_impact = 0;
         let placements = &solution.placements;
-        let volumes = &solution.volumes;
 
         // Musicians同士の衝突のみを考慮
         let non_blocked_placement_ids =
@@ -458,24 +966,50 @@ impl Input {
         let non_blocked_placement_ids = filter_placements_blocked_by_pillars(
             self.attendees[attendee_id].pos(),
             placements,
-            &self.pillars,
+            self.active_pillars(),
             &non_blocked_placement_ids,
         );
 
         for placement_id in non_blocked_placement_ids {
-            let volume = match volumes {
-                Some(v) => v[placement_id],
-                None => 1.0,
-            };
+            let volume = self.volume(solution, placement_id);
             // placement_id equals musician_id here
-            sum_impact += (volume
-                * impacts[placement_id]
-                * self.raw_impact(attendee_id, placement_id, &placements[placement_id]))
-            .ceil()
+            sum_impact += impact_score(
+                volume,
+                impacts[placement_id],
+                self.raw_impact(attendee_id, placement_id, &placements[placement_id]),
+            )
         }
         sum_impact
     }
 
+    // Playing togetherが無効なら全員1.0
+    pub fn playing_together_factors(&self, placements: &[Point]) -> Vec<f64> {
+        if self.rules.playing_together {
+            self.calc_playing_together(placements)
+        } else {
+            vec![1.0; self.musicians.len()]
+        }
+    }
+
+    // 1人分のPlaying togetherの倍率。calc_playing_togetherと同じ順で足す
+    pub fn playing_together_factor(&self, musician_id: MusicianId, placements: &[Point]) -> f64 {
+        if !self.rules.playing_together {
+            return 1.0;
+        }
+        let mut dists = vec![];
+        for (j, &m) in self.musicians.iter().enumerate() {
+            if j != musician_id && m == self.musicians[musician_id] {
+                dists.push(placements[musician_id].euclidean_distance(&placements[j]));
+            }
+        }
+        dists.sort_by(|a, b| b.partial_cmp(a).unwrap());
+        let mut score = 0.0;
+        for &d in dists.iter() {
+            score += 1.0 / d;
+        }
+        score + 1.0
+    }
+
     // Playing togetherによる各Musicianの得点倍率を計算する
     pub fn calc_playing_together(&self, placements: &[Point]) -> Vec<f64> {
         let mut inst_map = HashMap::new();
@@ -509,14 +1043,75 @@ impl Input {
         result
     }
 
-    #[cfg(not(target_arch = "wasm32"))]
-    pub fn score_fast(&self, solution: &Solution) -> Result<f64> {
-        let full_div = !self.pillars.is_empty();
-        let impacts = if full_div {
-            self.calc_playing_together(&solution.placements)
+    // musicianを置ける長方形。ステージの端と壁から10以上離れたところ
+    pub fn placeable_rect(&self) -> Option<(Point, Point)> {
+        const MUSICIAN_CLOSE_DIST: f64 = 10.0;
+        let (left, bottom) = self.stage_bottom_left.x_y();
+        let min = Point::new(
+            (left + MUSICIAN_CLOSE_DIST).max(MUSICIAN_CLOSE_DIST),
+            (bottom + MUSICIAN_CLOSE_DIST).max(MUSICIAN_CLOSE_DIST),
+        );
+        let max = Point::new(
+            (left + self.stage_width - MUSICIAN_CLOSE_DIST)
+                .min(self.room_width - MUSICIAN_CLOSE_DIST),
+            (bottom + self.stage_height - MUSICIAN_CLOSE_DIST)
+                .min(self.room_height - MUSICIAN_CLOSE_DIST),
+        );
+        (min.x() <= max.x() && min.y() <= max.y()).then_some((min, max))
+    }
+
+    // どう置いてもこれを超えないスコア
+    // 各musicianが観客ごとに一番近い置ける点にいて、遮られず、volumeが最大で、
+    // 同じ楽器の仲間が全員最短の10だけ離れているとして足す。負の項はvolume 0にすれば消せるので0とみなす
+    // 観客の上に置ける場合はいくらでも大きくなるのでNone
+    pub fn upper_bound(&self) -> Option<Score> {
+        const MUSICIAN_CLOSE_DIST: f64 = 10.0;
+        let Some((min, max)) = self.placeable_rect() else {
+            return Some(0);
+        };
+        let volume = if self.rules.volumes {
+            MAX_VOLUME
         } else {
-            vec![1.0; self.musicians.len()]
+            DEFAULT_VOLUME
         };
+        let mut multiplicities: HashMap<usize, usize> = HashMap::new();
+        for &m in &self.musicians {
+            *multiplicities.entry(m).or_default() += 1;
+        }
+        // calc_playing_togetherと同じ順で足して、丸めで実際の倍率を下回らないようにする
+        let closeness = |n: usize| {
+            if !self.rules.playing_together {
+                return 1.0;
+            }
+            let mut score = 0.0;
+            for _ in 1..n {
+                score += 1.0 / MUSICIAN_CLOSE_DIST;
+            }
+            score + 1.0
+        };
+        let mut bound: Score = 0;
+        for attendee in &self.attendees {
+            let (x, y) = attendee.pos().x_y();
+            let closest = Point::new(x.clamp(min.x(), max.x()), y.clamp(min.y(), max.y()));
+            let d = attendee.pos().euclidean_distance(&closest);
+            if d == 0.0 {
+                return None;
+            }
+            for (&instrument, &n) in &multiplicities {
+                let taste = attendee.tastes[instrument];
+                if taste <= 0.0 {
+                    continue;
+                }
+                let raw_impact = (1_000_000_f64 * taste / (d * d)).ceil();
+                bound += n as Score * impact_score(volume, closeness(n), raw_impact);
+            }
+        }
+        Some(bound)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn score_fast(&self, solution: &Solution) -> Result<Score> {
+        let impacts = self.playing_together_factors(&solution.placements);
         let ans = (0..self.attendees.len())
             .into_par_iter()
             .map(|attendee_id| self.score_attendee_fast(attendee_id, solution, &impacts))
@@ -524,15 +1119,10 @@ impl Input {
         Ok(ans)
     }
     #[cfg(target_arch = "wasm32")]
-    pub fn score_fast(&self, solution: &Solution) -> Result<f64> {
-        let full_div = !self.pillars.is_empty();
-        let impacts = if full_div {
-            self.calc_playing_together(&solution.placements)
-        } else {
-            vec![1.0; self.musicians.len()]
-        };
+    pub fn score_fast(&self, solution: &Solution) -> Result<Score> {
+        let impacts = self.playing_together_factors(&solution.placements);
         let ans = (0..self.attendees.len())
-            .map(|attendee_id| self.score_attendee_fast(attendee_id, &solution, &impacts))
+            .map(|attendee_id| self.score_attendee_fast(attendee_id, solution, &impacts))
             .sum();
         Ok(ans)
     }
@@ -545,9 +1135,56 @@ pub struct Solution {
 }
 
 impl Solution {
-    pub fn score(&self, input: &Input) -> Result<f64> {
+    // volumeをデフォルト値で明示的に埋めた解
+    pub fn new(placements: Vec<Point>) -> Self {
+        let volumes = vec![DEFAULT_VOLUME; placements.len()];
+        Solution {
+            placements,
+            volumes: Some(volumes),
+        }
+    }
+
+    // volumesがNoneならデフォルト値で埋める
+    pub fn with_volumes(placements: Vec<Point>, volumes: Option<Vec<f64>>) -> Self {
+        let mut solution = Solution {
+            placements,
+            volumes,
+        };
+        solution.fill_default_volumes();
+        solution
+    }
+
+    pub fn fill_default_volumes(&mut self) {
+        if self.volumes.is_none() {
+            self.volumes = Some(vec![DEFAULT_VOLUME; self.placements.len()]);
+        }
+    }
+
+    // 公式の仕様通り、volumeが指定されていなければ1.0
+    pub fn volume(&self, musician_id: MusicianId) -> f64 {
+        match &self.volumes {
+            Some(v) => v[musician_id],
+            None => DEFAULT_VOLUME,
+        }
+    }
+
+    pub fn volumes(&self) -> Vec<f64> {
+        match &self.volumes {
+            Some(v) => v.clone(),
+            None => vec![DEFAULT_VOLUME; self.placements.len()],
+        }
+    }
+
+    pub fn set_volume(&mut self, musician_id: MusicianId, volume: f64) {
+        self.fill_default_volumes();
+        if let Some(v) = &mut self.volumes {
+            v[musician_id] = volume;
+        }
+    }
+
+    pub fn score(&self, input: &Input) -> Result<Score> {
         // input.score(&self.placements)
-        input.is_valid_placements(&self.placements)?;
+        input.check_solution(self)?;
         input.score_fast(self)
     }
 }
@@ -643,57 +1280,255 @@ mod tests {
     #[test]
     fn sample_eval() {
         let input_str = std::fs::read_to_string("./testdata/sample-input.json").unwrap();
-        let input: Input = serde_json::from_str(&input_str).unwrap();
+        let mut input: Input = serde_json::from_str(&input_str).unwrap();
+        input.rules = ScoringRules::LIGHTNING;
         let solution_str = std::fs::read_to_string("./testdata/sample-output.json").unwrap();
         let solution: Solution = serde_json::from_str(&solution_str).unwrap();
         let score = solution.score(&input).unwrap();
-        assert_eq!(score, 5343.0);
+        assert_eq!(score, 5343);
+    }
+    #[test]
+    fn sample_eval_full_rules_without_pillars() {
+        let input_str = std::fs::read_to_string("./testdata/sample-input.json").unwrap();
+        let mut input: Input = serde_json::from_str(&input_str).unwrap();
+        input.rules = ScoringRules::FULL;
+        let solution_str = std::fs::read_to_string("./testdata/sample-output.json").unwrap();
+        let solution: Solution = serde_json::from_str(&solution_str).unwrap();
+        let score = solution.score(&input).unwrap();
+        assert_eq!(score, 5357);
+        assert_eq!(input.score(&solution.placements).unwrap(), 5357);
+    }
+    #[test]
+    fn impact_score_rounding() {
+        assert_eq!(impact_score(1.0, 1.0, 5.0), 5);
+        assert_eq!(impact_score(0.5, 1.0, 3.0), 2);
+        assert_eq!(impact_score(0.5, 1.0, -3.0), -1);
+        assert_eq!(impact_score(0.0, 2.0, 1234.0), 0);
+        // f64で足すと2^53を超えたところで1が消える
+        let big = impact_score(MAX_VOLUME, 1.0, (1u64 << 53) as f64);
+        assert_eq!(big + impact_score(1.0, 1.0, 1.0), 10 * (1 << 53) + 1);
+    }
+
+    #[test]
+    fn upper_bound_is_above_score() {
+        let input = crate::read_input("./testdata/problem-80.json", None).unwrap();
+        let solution_str = std::fs::read_to_string("./testdata/solution-80.json").unwrap();
+        let solution: Solution = serde_json::from_str(&solution_str).unwrap();
+        let bound = input.upper_bound().unwrap();
+        assert!(input.score_fast(&solution).unwrap() <= bound);
+        let mut rng = rand_pcg::Pcg64Mcg::new(2);
+        for rules in [ScoringRules::LIGHTNING, ScoringRules::FULL] {
+            for _ in 0..20 {
+                let case = crate::cross_check::random_case(&mut rng, rules);
+                let score = case.input.score_fast(&case.solution).unwrap();
+                assert!(case
+                    .input
+                    .upper_bound()
+                    .map_or(true, |bound| score <= bound));
+            }
+        }
+        // 楽器1つの観客1人なら、1人を一番近い点に置いたときと一致する
+        let mut input = Input {
+            room_width: 100.0,
+            room_height: 100.0,
+            stage_width: 40.0,
+            stage_height: 40.0,
+            stage_bottom_left: Point::new(0.0, 0.0),
+            musicians: vec![0],
+            attendees: vec![Attendee {
+                x: 80.0,
+                y: 20.0,
+                tastes: vec![1000.0],
+            }],
+            pillars: vec![],
+            rules: ScoringRules::FULL,
+        };
+        let solution = Solution::with_volumes(vec![Point::new(30.0, 20.0)], Some(vec![MAX_VOLUME]));
+        assert_eq!(
+            input.upper_bound(),
+            Some(input.score_fast(&solution).unwrap())
+        );
+        input.attendees[0].tastes[0] = -1000.0;
+        assert_eq!(input.upper_bound(), Some(0));
+        // 観客の上にも置けるなら有限の上界はない
+        input.attendees[0].x = 30.0;
+        assert_eq!(input.upper_bound(), None);
+    }
+
+    #[test]
+    fn score_breakdown_matches_score() {
+        let input = crate::read_input("./testdata/problem-80.json", None).unwrap();
+        let solution_str = std::fs::read_to_string("./testdata/solution-80.json").unwrap();
+        let solution: Solution = serde_json::from_str(&solution_str).unwrap();
+        let breakdown = input.score_breakdown(&solution).unwrap();
+        assert_eq!(breakdown.score, 18886452);
+        assert_eq!(breakdown.attendees.len(), input.attendees.len());
+        let musician_total: Score = breakdown.musicians.iter().map(|m| m.score).sum();
+        assert_eq!(musician_total, breakdown.score);
+        for attendee in breakdown.attendees.iter() {
+            for impact in attendee.impacts.iter() {
+                assert_eq!(
+                    impact.blocked_by.is_none(),
+                    attendee.matched_musician_ids.contains(&impact.musician_id)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn score_breakdown_reports_blocker() {
+        let input_str = std::fs::read_to_string("./testdata/sample-input.json").unwrap();
+        let mut input: Input = serde_json::from_str(&input_str).unwrap();
+        input.rules = ScoringRules::LIGHTNING;
+        let solution_str = std::fs::read_to_string("./testdata/sample-output.json").unwrap();
+        let solution: Solution = serde_json::from_str(&solution_str).unwrap();
+        let breakdown = input.score_breakdown(&solution).unwrap();
+        assert_eq!(breakdown.score, 5343);
+        // 公式の例: attendee 2 から musician 1 への線分は musician 2 に遮られる
+        assert_eq!(
+            breakdown.attendees[2].impacts[1].blocked_by,
+            Some(Blocker::Musician(2))
+        );
+    }
+
+    #[test]
+    fn validation_collects_every_violation() {
+        let input_str = std::fs::read_to_string("./testdata/sample-input.json").unwrap();
+        let input: Input = serde_json::from_str(&input_str).unwrap();
+        let solution = Solution {
+            placements: vec![
+                Point::new(590.0, 10.0),
+                Point::new(595.0, 10.0),
+                Point::new(f64::NAN, 150.0),
+            ],
+            volumes: Some(vec![1.0, 11.0, 1.0]),
+        };
+        let errors = input.check_solution(&solution).unwrap_err();
+        assert_eq!(errors.0.len(), 3);
+        assert!(matches!(
+            errors.0[0],
+            ValidationError::NanCoordinates { musician: 2, .. }
+        ));
+        assert_eq!(
+            errors.0[1],
+            ValidationError::MusiciansTooClose {
+                musicians: (0, 1),
+                dist: 5.0
+            }
+        );
+        assert_eq!(
+            errors.0[2],
+            ValidationError::BadVolume {
+                musician: 1,
+                volume: 11.0
+            }
+        );
+        assert_eq!(errors.musicians(), vec![0, 1, 2]);
+        // 最初の違反だけを見るモード
+        assert!(input.is_valid_placements(&solution.placements).is_err());
+        assert_eq!(input.placement_violations(&solution.placements).len(), 2);
+    }
+
+    #[test]
+    fn solution_volumes() {
+        let input_str = std::fs::read_to_string("./testdata/sample-input.json").unwrap();
+        let input: Input = serde_json::from_str(&input_str).unwrap();
+        let solution_str = std::fs::read_to_string("./testdata/sample-output.json").unwrap();
+        let solution: Solution = serde_json::from_str(&solution_str).unwrap();
+        assert!(solution.volumes.is_none());
+        let explicit = Solution::with_volumes(solution.placements.clone(), None);
+        assert_eq!(explicit.volumes, Some(vec![1.0; 3]));
+        assert_eq!(
+            solution.score(&input).unwrap(),
+            explicit.score(&input).unwrap()
+        );
+
+        let mut short = explicit.clone();
+        short.volumes = Some(vec![1.0; 2]);
+        let errors = input.check_solution(&short).unwrap_err();
+        assert_eq!(
+            errors.0,
+            vec![ValidationError::WrongVolumeCount {
+                expected: 3,
+                actual: 2
+            }]
+        );
+        assert!(short.score(&input).is_err());
+
+        let mut loud = explicit;
+        loud.set_volume(0, 10.0);
+        assert!(loud.score(&input).is_ok());
+        loud.set_volume(0, f64::INFINITY);
+        assert!(loud.score(&input).is_err());
+    }
+
+    #[test]
+    fn parse_scoring_rules() {
+        assert_eq!(
+            "lightning".parse::<ScoringRules>().unwrap(),
+            ScoringRules::LIGHTNING
+        );
+        assert_eq!("full".parse::<ScoringRules>().unwrap(), ScoringRules::FULL);
+        let rules: ScoringRules = "pillars,volumes".parse().unwrap();
+        assert!(rules.pillars && !rules.playing_together && rules.volumes);
+        assert_eq!(rules.to_string().parse::<ScoringRules>().unwrap(), rules);
+        assert!("gravity".parse::<ScoringRules>().is_err());
+        assert_eq!(
+            ScoringRules::for_problem_id(55),
+            ScoringRules::official(false)
+        );
+        assert_eq!(ScoringRules::for_problem_id(56), ScoringRules::FULL);
     }
     #[test]
     fn sample_eval_with_full() {
         let input_str =
             std::fs::read_to_string("./testdata/sample-input-with-pillars.json").unwrap();
-        let input: Input = serde_json::from_str(&input_str).unwrap();
+        let mut input: Input = serde_json::from_str(&input_str).unwrap();
+        input.rules = ScoringRules::FULL;
         let solution_str = std::fs::read_to_string("./testdata/sample-output.json").unwrap();
         let solution: Solution = serde_json::from_str(&solution_str).unwrap();
         let score = solution.score(&input).unwrap();
-        assert_eq!(score, 5357.0);
+        assert_eq!(score, 5357);
     }
     #[test]
     fn sample_eval2() {
         let input_str = std::fs::read_to_string("./testdata/problem-1.json").unwrap();
-        let input: Input = serde_json::from_str(&input_str).unwrap();
+        let mut input: Input = serde_json::from_str(&input_str).unwrap();
+        input.rules = ScoringRules::for_problem_id(1);
         let solution_str = std::fs::read_to_string("./testdata/solution-1.json").unwrap();
         let solution: Solution = serde_json::from_str(&solution_str).unwrap();
         let score = solution.score(&input).unwrap();
-        assert_eq!(score, 505006687.0);
+        assert_eq!(score, 505006687);
     }
     #[test]
     fn sample_eval3() {
         let input_str = std::fs::read_to_string("./testdata/problem-29.json").unwrap();
-        let input: Input = serde_json::from_str(&input_str).unwrap();
+        let mut input: Input = serde_json::from_str(&input_str).unwrap();
+        input.rules = ScoringRules::for_problem_id(29);
         let solution_str = std::fs::read_to_string("./testdata/solution-29.json").unwrap();
         let solution: Solution = serde_json::from_str(&solution_str).unwrap();
         let score = solution.score(&input).unwrap();
-        assert_eq!(score, 109646092.0);
+        assert_eq!(score, 109646092);
     }
     #[test]
     fn sample_full_small_eval() {
         let input_str = std::fs::read_to_string("./testdata/sample-small-full-input.json").unwrap();
-        let input: Input = serde_json::from_str(&input_str).unwrap();
+        let mut input: Input = serde_json::from_str(&input_str).unwrap();
+        input.rules = ScoringRules::FULL;
         let solution_str = std::fs::read_to_string("./testdata/sample-output.json").unwrap();
         let solution: Solution = serde_json::from_str(&solution_str).unwrap();
         let score = solution.score(&input).unwrap();
-        assert_eq!(score, 3459.0);
+        assert_eq!(score, 3459);
     }
     #[test]
     fn sample_full_eval2() {
         let input_str = std::fs::read_to_string("./testdata/problem-80.json").unwrap();
-        let input: Input = serde_json::from_str(&input_str).unwrap();
+        let mut input: Input = serde_json::from_str(&input_str).unwrap();
+        input.rules = ScoringRules::for_problem_id(80);
         let solution_str = std::fs::read_to_string("./testdata/solution-80.json").unwrap();
         let solution: Solution = serde_json::from_str(&solution_str).unwrap();
         let score = solution.score(&input).unwrap();
-        assert_eq!(score, 18886452.0);
+        assert_eq!(score, 18886452);
     }
 
     // TODO: 公式のジャッジサーバーとはスコアが異なっている
@@ -701,10 +1536,11 @@ mod tests {
     #[ignore]
     fn sample_full_eval() {
         let input_str = std::fs::read_to_string("./testdata/sample-full-input.json").unwrap();
-        let input: Input = serde_json::from_str(&input_str).unwrap();
+        let mut input: Input = serde_json::from_str(&input_str).unwrap();
+        input.rules = ScoringRules::FULL;
         let solution_str = std::fs::read_to_string("./testdata/sample-full-output.json").unwrap();
         let solution: Solution = serde_json::from_str(&solution_str).unwrap();
         let score = solution.score(&input).unwrap();
-        assert_eq!(score, 15894740.0);
+        assert_eq!(score, 15894740);
     }
 }
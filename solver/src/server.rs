@@ -0,0 +1,517 @@
+// チームのサーバー (webapp/fastapi) の代わりに手元で動かすHTTPサーバー
+// 同じエンドポイントを、このcrateの検証と採点、archiveへの保存で実装する
+use crate::archive::{Archive, Entry};
+use crate::problem::Solution;
+use anyhow::{bail, Context, Result};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::Arc;
+
+#[derive(Debug, Default)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub query: HashMap<String, String>,
+    // 名前は小文字にしておく
+    pub headers: HashMap<String, String>,
+    pub body: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct Response {
+    pub status: u16,
+    pub body: Value,
+}
+
+impl Response {
+    fn ok(body: Value) -> Self {
+        Response { status: 200, body }
+    }
+
+    fn error(status: u16, message: impl ToString) -> Self {
+        Response {
+            status,
+            body: json!({ "message": message.to_string() }),
+        }
+    }
+}
+
+// %XXと+を戻す
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => out.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok();
+                match hex.and_then(|h| u8::from_str_radix(h, 16).ok()) {
+                    Some(b) => {
+                        out.push(b);
+                        i += 2;
+                    }
+                    None => out.push(b'%'),
+                }
+            }
+            b => out.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+// 変なリクエストでメモリを使い切らないように上限を決めておく
+const MAX_LINE: u64 = 8 * 1024;
+const MAX_HEADERS: usize = 100;
+const MAX_BODY: usize = 64 * 1024 * 1024;
+
+fn read_line(reader: &mut impl BufRead, line: &mut String) -> Result<()> {
+    line.clear();
+    reader.take(MAX_LINE + 1).read_line(line)?;
+    if line.len() as u64 > MAX_LINE {
+        bail!("line too long");
+    }
+    Ok(())
+}
+
+pub fn read_request(reader: &mut impl BufRead) -> Result<Request> {
+    let mut line = String::new();
+    read_line(reader, &mut line)?;
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        bail!("bad request line: {:?}", line)
+    };
+    let mut request = Request {
+        method: method.to_owned(),
+        ..Default::default()
+    };
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    request.path = path.to_owned();
+    for pair in query.split('&').filter(|p| !p.is_empty()) {
+        let (k, v) = pair.split_once('=').unwrap_or((pair, ""));
+        request.query.insert(percent_decode(k), percent_decode(v));
+    }
+    loop {
+        read_line(reader, &mut line)?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if request.headers.len() >= MAX_HEADERS {
+            bail!("too many headers");
+        }
+        if let Some((k, v)) = line.split_once(':') {
+            request
+                .headers
+                .insert(k.trim().to_ascii_lowercase(), v.trim().to_owned());
+        }
+    }
+    if let Some(len) = request.headers.get("content-length") {
+        let len: usize = len.parse().context("bad content-length")?;
+        if len > MAX_BODY {
+            bail!("body too large: {len} bytes");
+        }
+        request.body = vec![0; len];
+        reader.read_exact(&mut request.body)?;
+    }
+    Ok(request)
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+// multipart/form-dataから、nameがfieldの部分の中身を取り出す
+pub fn multipart_field(request: &Request, field: &str) -> Result<Vec<u8>> {
+    let content_type = request
+        .headers
+        .get("content-type")
+        .context("no content-type")?;
+    let Some((_, boundary)) = content_type.split_once("boundary=") else {
+        bail!("not multipart: {content_type}")
+    };
+    let delimiter = format!("--{}", boundary.trim_matches('"'));
+    let mut rest = &request.body[..];
+    while let Some(start) = find(rest, delimiter.as_bytes()) {
+        rest = &rest[start + delimiter.len()..];
+        let Some(header_end) = find(rest, b"\r\n\r\n") else { break };
+        let headers = String::from_utf8_lossy(&rest[..header_end]);
+        let body = &rest[header_end + 4..];
+        let Some(end) = find(body, format!("\r\n{delimiter}").as_bytes()) else { break };
+        if headers.contains(&format!("name=\"{field}\"")) {
+            return Ok(body[..end].to_vec());
+        }
+        rest = &body[end..];
+    }
+    bail!("no field {field} in the form")
+}
+
+pub struct Server {
+    archive: Archive,
+    problems_dir: String,
+}
+
+impl Server {
+    pub fn new(archive: Archive, problems_dir: &str) -> Self {
+        Server {
+            archive,
+            problems_dir: problems_dir.to_owned(),
+        }
+    }
+
+    pub fn handle(&self, request: &Request) -> Response {
+        let result = match (request.method.as_str(), request.path.as_str()) {
+            ("POST", "/api/solutions/submit") => self.submit(request),
+            ("GET", "/api/best_solutions") => self.best_solution(request),
+            // 提出したときに採点するので、後から更新するものはない
+            ("POST", "/api/solutions/update_score") => Ok(Response::ok(json!({
+                "updated": 0,
+                "processing": 0,
+                "error": 0,
+            }))),
+            _ => Ok(Response::error(404, "not found")),
+        };
+        result.unwrap_or_else(|e| Response::error(400, format!("{:#}", e)))
+    }
+
+    fn problem_id(request: &Request) -> Result<u32> {
+        let id = request.query.get("id").context("no id")?;
+        id.parse().with_context(|| format!("bad id: {id}"))
+    }
+
+    // 正しくない解は保存せず、statusをfailedにして返す
+    fn submit(&self, request: &Request) -> Result<Response> {
+        let id = Self::problem_id(request)?;
+        let solver = request.query.get("solver").map_or("unknown", |s| s);
+        let contents = multipart_field(request, "file")?;
+        let input = crate::read_input(&format!("{}/problem-{id}.json", self.problems_dir), None)?;
+        let solution: Solution = match serde_json::from_slice(&contents) {
+            Ok(solution) => solution,
+            Err(e) => {
+                return Ok(Response::ok(
+                    json!({ "status": "failed", "error": e.to_string() }),
+                ))
+            }
+        };
+        if let Err(errors) = input.check_solution(&solution) {
+            return Ok(Response::ok(
+                json!({ "status": "failed", "error": errors.to_string() }),
+            ));
+        }
+        let entry = self.archive.insert(id, &input, &solution, solver, 0)?;
+        println!("problem {}: {} submitted {}", id, solver, entry.score);
+        Ok(Response::ok(json!({
+            "submission_id": submission_id(&entry),
+            "status": "success",
+            "score": entry.score,
+        })))
+    }
+
+    // tsがあれば、それより前に提出したものの中でのベスト (FastAPIの ts < %s と同じ)
+    // idはDBの行番号の代わりに、その問題の何番目に入った解か (1から)
+    fn best_solution(&self, request: &Request) -> Result<Response> {
+        let id = Self::problem_id(request)?;
+        let before = match request.query.get("ts") {
+            Some(ts) => Some(parse_ts(ts)?),
+            None => None,
+        };
+        let history = self.archive.history(id)?;
+        // スコアが同じなら先に入ったもの
+        let best = history
+            .iter()
+            .enumerate()
+            .filter(|(_, e)| before.map_or(true, |ts| e.timestamp < ts))
+            .rev()
+            .max_by_key(|(_, e)| e.score);
+        let Some((row, entry)) = best else {
+            return Ok(Response::ok(json!({ "message": "not found" })));
+        };
+        Ok(Response::ok(json!({
+            "id": row + 1,
+            "problem_id": entry.problem_id,
+            "submission_id": submission_id(entry),
+            "solver": entry.solver,
+            "status": "success",
+            "score": entry.score,
+            "ts": format_ts(entry.timestamp),
+            "contents": serde_json::to_string(&entry.solution)?,
+        })))
+    }
+}
+
+// コンテストのサーバーのsubmission_idと同じ24桁の16進数にする
+fn submission_id(entry: &Entry) -> String {
+    format!("{:016x}{:08x}", entry.timestamp, entry.problem_id)
+}
+
+// 1970-01-01からの日数と、UTCの年月日の変換
+fn days_from_civil(y: i64, m: i64, d: i64) -> i64 {
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let doy = (153 * (m + if m > 2 { -3 } else { 9 }) + 2) / 5 + d - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    (
+        if m <= 2 {
+            yoe + era * 400 + 1
+        } else {
+            yoe + era * 400
+        },
+        m,
+        d,
+    )
+}
+
+// FastAPIが返すtsと同じ "2023-07-09T12:00:00" (UTC)
+fn format_ts(unix_ms: u64) -> String {
+    let secs = (unix_ms / 1000) as i64;
+    let (y, m, d) = civil_from_days(secs.div_euclid(86400));
+    let t = secs.rem_euclid(86400);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+        y,
+        m,
+        d,
+        t / 3600,
+        t / 60 % 60,
+        t % 60
+    )
+}
+
+// "2023-07-09 12:00:00", "2023-07-09T12:00:00.5", "2023-07-09" (UTC) か、unix time (ミリ秒)
+fn parse_ts(s: &str) -> Result<u64> {
+    let s = s.trim();
+    if !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit()) {
+        return s.parse().with_context(|| format!("bad ts: {s}"));
+    }
+    let parse = || -> Option<u64> {
+        let (date, time) = s
+            .split_once(|c| c == ' ' || c == 'T')
+            .unwrap_or((s, "00:00:00"));
+        let date: Vec<i64> = date
+            .split('-')
+            .map(|n| n.parse().ok())
+            .collect::<Option<_>>()?;
+        let (time, frac) = time.split_once('.').unwrap_or((time, ""));
+        let time: Vec<i64> = time
+            .split(':')
+            .map(|n| n.parse().ok())
+            .collect::<Option<_>>()?;
+        let (&[y, m, d], &[h, mi, sec]) = (&date[..], &time[..]) else { return None };
+        if !(1..=12).contains(&m) || !(1..=31).contains(&d) || h >= 24 || mi >= 60 || sec >= 60 {
+            return None;
+        }
+        let ms = match frac {
+            "" => 0,
+            f if f.bytes().all(|b| b.is_ascii_digit()) => {
+                format!("{:0<3}", f)[..3].parse::<i64>().ok()?
+            }
+            _ => return None,
+        };
+        let secs = days_from_civil(y, m, d) * 86400 + h * 3600 + mi * 60 + sec;
+        u64::try_from(secs * 1000 + ms).ok()
+    };
+    parse().with_context(|| format!("bad ts: {s}"))
+}
+
+fn respond(stream: &mut TcpStream, response: &Response) -> Result<()> {
+    let body = response.body.to_string();
+    let reason = match response.status {
+        200 => "OK",
+        404 => "Not Found",
+        _ => "Bad Request",
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        response.status,
+        reason,
+        body.len(),
+        body
+    )?;
+    Ok(())
+}
+
+// 1接続1リクエストで、接続ごとにスレッドを立てる
+pub fn serve(addr: &str, server: Server) -> Result<()> {
+    let listener = TcpListener::bind(addr).with_context(|| format!("failed to bind {addr}"))?;
+    println!("listening on {}", addr);
+    let server = Arc::new(server);
+    for stream in listener.incoming() {
+        let mut stream = stream?;
+        let server = server.clone();
+        std::thread::spawn(move || {
+            let response = match read_request(&mut BufReader::new(&mut stream)) {
+                Ok(request) => server.handle(&request),
+                Err(e) => Response::error(400, format!("{:#}", e)),
+            };
+            if let Err(e) = respond(&mut stream, &response) {
+                eprintln!("failed to respond: {:#}", e);
+            }
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cross_check::random_case;
+    use crate::problem::ScoringRules;
+    use rand_pcg::Pcg64Mcg;
+
+    fn submit_request(id: u32, solver: &str, contents: &str) -> Vec<u8> {
+        let body = format!(
+            "--XyZ\r\nContent-Disposition: form-data; name=\"file\"; filename=\"a.json\"\r\n\
+             Content-Type: application/json\r\n\r\n{contents}\r\n--XyZ--\r\n"
+        );
+        format!(
+            "POST /api/solutions/submit?id={id}&solver={solver} HTTP/1.1\r\n\
+             Host: localhost:8080\r\nContent-Type: multipart/form-data; boundary=XyZ\r\n\
+             Content-Length: {}\r\n\r\n{body}",
+            body.len()
+        )
+        .into_bytes()
+    }
+
+    #[test]
+    fn parse_request() {
+        assert_eq!(percent_decode("random%28abc%29+x%"), "random(abc) x%");
+        let raw = submit_request(3, "a%2Bb", "{}");
+        let request = read_request(&mut &raw[..]).unwrap();
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/api/solutions/submit");
+        assert_eq!(request.query["id"], "3");
+        assert_eq!(request.query["solver"], "a+b");
+        assert_eq!(multipart_field(&request, "file").unwrap(), b"{}");
+        assert!(multipart_field(&request, "other").is_err());
+    }
+
+    #[test]
+    fn reject_huge_request() {
+        let raw = format!(
+            "POST / HTTP/1.1\r\nContent-Length: {}\r\n\r\n",
+            MAX_BODY + 1
+        );
+        let e = read_request(&mut raw.as_bytes()).unwrap_err();
+        assert!(e.to_string().contains("too large"), "{e}");
+        let raw = format!("GET /{} HTTP/1.1\r\n\r\n", "a".repeat(MAX_LINE as usize));
+        assert!(read_request(&mut raw.as_bytes()).is_err());
+        let raw = format!(
+            "GET / HTTP/1.1\r\n{}\r\n",
+            (0..=MAX_HEADERS)
+                .map(|i| format!("X-{i}: b\r\n"))
+                .collect::<String>()
+        );
+        assert!(read_request(&mut raw.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn timestamps() {
+        assert_eq!(format_ts(0), "1970-01-01T00:00:00");
+        assert_eq!(format_ts(1_688_904_000_999), "2023-07-09T12:00:00");
+        assert_eq!(format_ts(951_782_400_000), "2000-02-29T00:00:00");
+        for s in [
+            "2023-07-09 12:00:00",
+            "2023-07-09T12:00:00",
+            "1688904000000",
+        ] {
+            assert_eq!(parse_ts(s).unwrap(), 1_688_904_000_000, "{s}");
+        }
+        assert_eq!(parse_ts("2023-07-09").unwrap(), 1_688_860_800_000);
+        assert_eq!(
+            parse_ts("2023-07-09 12:00:00.25").unwrap(),
+            1_688_904_000_250
+        );
+        for ms in [0, 951_782_400_000, 1_688_904_000_000, 4_102_444_800_000] {
+            assert_eq!(parse_ts(&format_ts(ms)).unwrap(), ms);
+        }
+        for s in [
+            "yesterday",
+            "2023-13-01",
+            "2023-07-09 24:00:00",
+            "2023-07-09 12:00",
+            "",
+        ] {
+            assert!(parse_ts(s).is_err(), "{s}");
+        }
+    }
+
+    #[test]
+    fn submit_and_best() {
+        let mut rng = Pcg64Mcg::new(6);
+        let case = random_case(&mut rng, ScoringRules::FULL);
+        let dir = std::env::temp_dir().join(format!("server-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        // 56番以降はfullのルールで採点される
+        std::fs::write(
+            dir.join("problem-56.json"),
+            serde_json::to_string(&case.input).unwrap(),
+        )
+        .unwrap();
+        let archive = Archive::open(dir.join("archive").to_str().unwrap()).unwrap();
+        let server = Server::new(archive, dir.to_str().unwrap());
+        let get = |raw: &[u8]| server.handle(&read_request(&mut &raw[..]).unwrap());
+
+        let response = get(b"GET /api/best_solutions?id=56 HTTP/1.1\r\n\r\n");
+        assert_eq!(response.body["message"], "not found");
+
+        let contents = serde_json::to_string(&case.solution).unwrap();
+        let response = get(&submit_request(56, "test", &contents));
+        assert_eq!(response.status, 200);
+        assert_eq!(response.body["status"], "success");
+        let score = case.input.score_fast(&case.solution).unwrap();
+        assert_eq!(response.body["score"], score as i64);
+
+        let response = get(&submit_request(56, "test", "{\"placements\": []}"));
+        assert_eq!(response.body["status"], "failed");
+
+        let response = get(b"GET /api/best_solutions?id=56 HTTP/1.1\r\n\r\n");
+        assert_eq!(response.body["score"], score as i64);
+        assert_eq!(response.body["solver"], "test");
+        let best: Solution =
+            serde_json::from_str(response.body["contents"].as_str().unwrap()).unwrap();
+        assert_eq!(best.placements, case.solution.placements);
+
+        assert_eq!(response.body["id"], 1);
+        assert_eq!(response.body["submission_id"].as_str().unwrap().len(), 24);
+        // FastAPIのクライアントと同じく、日時の文字列で送る
+        let ts = response.body["ts"].as_str().unwrap();
+        assert_eq!(ts.len(), "2023-07-09T12:00:00".len());
+        let before = |ts: &str| {
+            get(format!("GET /api/best_solutions?id=56&ts={ts} HTTP/1.1\r\n\r\n").as_bytes())
+        };
+        let next_second = format_ts(parse_ts(ts).unwrap() + 1000).replace('T', "%20");
+        assert_eq!(before(&next_second).body["score"], score as i64);
+        assert_eq!(before(&ts.replace('T', "+")).body["message"], "not found");
+        assert_eq!(before("2000-01-01").body["message"], "not found");
+        assert_eq!(
+            get(b"GET /api/best_solutions?id=56&ts=yesterday HTTP/1.1\r\n\r\n").status,
+            400
+        );
+
+        let response = get(b"POST /api/solutions/update_score HTTP/1.1\r\n\r\n");
+        assert_eq!(response.body["error"], 0);
+        assert_eq!(get(b"GET /api/nothing HTTP/1.1\r\n\r\n").status, 404);
+        assert_eq!(
+            get(b"GET /api/best_solutions?id=x HTTP/1.1\r\n\r\n").status,
+            400
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
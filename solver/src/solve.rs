@@ -0,0 +1,767 @@
+// 初期配置を作る戦略。solverコマンドの`solve <strategy>`から呼ぶ
+use crate::annealing::{Acceptance, Annealer, Config, Schedule, Selection};
+use crate::budget::Budget;
+use crate::garasubo_util::{ShieldMove, VolumeMove};
+use crate::incremental::IncrementalScorer;
+use crate::problem::*;
+use crate::solver_util::volume_optimize;
+use crate::PlacementGenerator;
+use anyhow::{bail, Result};
+use geo::{EuclideanDistance, Point};
+use ordered_float::OrderedFloat;
+use pathfinding::kuhn_munkres::kuhn_munkres;
+use pathfinding::matrix::Matrix;
+use rand::seq::{IteratorRandom, SliceRandom};
+use rand::Rng;
+use rand_pcg::Pcg64Mcg;
+use rayon::prelude::*;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::ops::Mul;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strategy {
+    // 蜂の巣状の候補をシャッフルする
+    Random,
+    // 20間隔の格子をシャッフルする
+    Random2,
+    // 観客に近づくように勾配法で動かす
+    Sgd,
+    // ランダムな候補に割当問題を解いて当てはめる
+    RandomExact,
+    // ステージの縁に近い候補から2段階で割当問題を解き、volumeも決める
+    RandomExactRect,
+    // RandomExactRectの配置から、playing togetherを考えて入れ替える
+    TwoStageExactTogether,
+    // 人気のある楽器から順に、同じ楽器を隣に固めて置く
+    Garasubo,
+    // RandomExactRectの配置から、volume 0のmusicianを盾にして負の嗜好の観客の視線を遮る
+    Shield,
+}
+
+impl Strategy {
+    pub const ALL: [Strategy; 8] = [
+        Strategy::Random,
+        Strategy::Random2,
+        Strategy::Sgd,
+        Strategy::RandomExact,
+        Strategy::RandomExactRect,
+        Strategy::TwoStageExactTogether,
+        Strategy::Garasubo,
+        Strategy::Shield,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Strategy::Random => "random",
+            Strategy::Random2 => "random2",
+            Strategy::Sgd => "sgd",
+            Strategy::RandomExact => "random_exact",
+            Strategy::RandomExactRect => "random_exact_rect",
+            Strategy::TwoStageExactTogether => "two_stage_exact_together",
+            Strategy::Garasubo => "garasubo",
+            Strategy::Shield => "shield",
+        }
+    }
+}
+
+impl fmt::Display for Strategy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Strategy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match Strategy::ALL.iter().find(|st| st.name() == s) {
+            Some(&st) => Ok(st),
+            None => bail!(
+                "unknown strategy: {s} (one of {})",
+                Strategy::ALL.map(|st| st.name()).join(", ")
+            ),
+        }
+    }
+}
+
+// budgetを使い切るまで探して一番良い配置を返す。配置が正しいかは呼び出し側で確かめる
+pub fn solve(input: &Input, strategy: Strategy, seed: u128, budget: &Budget) -> Result<Solution> {
+    Ok(match strategy {
+        Strategy::Random => random(input, seed, budget),
+        Strategy::Random2 => random2(input, seed, budget),
+        Strategy::Sgd => sgd(input, seed, budget),
+        Strategy::RandomExact => random_exact(input, seed, budget)?,
+        Strategy::RandomExactRect => random_exact_rect(input, seed),
+        Strategy::TwoStageExactTogether => two_stage_exact_together(input, seed, budget),
+        Strategy::Garasubo => garasubo(input, seed, budget),
+        Strategy::Shield => shield(input, seed, budget),
+    })
+}
+
+pub fn random(input: &Input, seed: u128, budget: &Budget) -> Solution {
+    let mut candidates = PlacementGenerator::honeycomb_candidates(input);
+    // 候補がmusicianより多いときは先頭から使う
+    let take = |candidates: &[Point]| Solution {
+        placements: candidates
+            .iter()
+            .take(input.musicians.len())
+            .cloned()
+            .collect(),
+        ..Default::default()
+    };
+
+    let mut rnd = Pcg64Mcg::new(seed);
+    let mut best_solution = take(&candidates);
+    let mut best_score = best_solution.score(input).unwrap_or(Score::MIN);
+    eprintln!("initial score: {}", best_score);
+    let mut iter = 0;
+    while !budget.is_exhausted(iter) {
+        candidates.shuffle(&mut rnd);
+        let solution = take(&candidates);
+
+        if let Ok(score) = solution.score(input) {
+            if score > best_score {
+                best_score = score;
+                best_solution = solution.clone();
+                eprintln!("iter {}, score: {}", iter, best_score);
+            }
+        }
+        iter += 1;
+    }
+    best_solution
+}
+
+pub fn random2(input: &Input, seed: u128, budget: &Budget) -> Solution {
+    let mut candidates = PlacementGenerator::cartesian_coordinate_candidates(input);
+    let mut best_solution = Solution {
+        placements: candidates
+            .iter()
+            .take(input.musicians.len())
+            .cloned()
+            .collect(),
+        ..Default::default()
+    };
+
+    let mut rnd = Pcg64Mcg::new(seed);
+    let mut best_score = best_solution.score(input).unwrap_or(Score::MIN);
+    eprintln!("initial score: {}", best_score);
+    let mut i = 0;
+    while !budget.is_exhausted(i) {
+        candidates.shuffle(&mut rnd);
+        let solution = Solution {
+            placements: candidates
+                .iter()
+                .take(input.musicians.len())
+                .cloned()
+                .collect(),
+            ..Default::default()
+        };
+
+        if let Ok(score) = solution.score(input) {
+            if score > best_score {
+                best_score = score;
+                best_solution = solution;
+                eprintln!("iter {}, score: {}", i, best_score);
+            }
+        }
+        i += 1;
+    }
+    best_solution
+}
+
+pub fn sgd(input: &Input, seed: u128, budget: &Budget) -> Solution {
+    let mut solution: Solution = Default::default();
+    let leftlimit = input.stage_bottom_left.x() + 10.0;
+    let rightlimit = input.stage_bottom_left.x() + input.stage_width - 10.0;
+    let bottomlimit = input.stage_bottom_left.y();
+    let toplimit = input.stage_bottom_left.y() + input.stage_height - 10.0;
+
+    let mut rng = Pcg64Mcg::new(seed);
+    for _i in 0..input.musicians.len() {
+        let x = rng.gen_range(leftlimit..rightlimit);
+        let y = rng.gen_range(bottomlimit..toplimit);
+        solution.placements.push(Point::new(x, y));
+    }
+
+    let mut best_solution = solution.clone();
+    let mut best_score = solution.score(input).unwrap_or(Score::MIN);
+    eprintln!("initial score: {}", best_score);
+
+    let mut permutation = vec![];
+    for i in 0..input.attendees.len() {
+        for k in 0..input.musicians.len() {
+            permutation.push((i, k));
+        }
+    }
+
+    let rate = 0.1;
+    let mut i = 0;
+    while !budget.is_exhausted(i) {
+        permutation.shuffle(&mut rng);
+        for (i, k) in &permutation {
+            let a = &input.attendees[*i];
+            let a = Point::new(a.x, a.y);
+            let d = solution.placements[*k].euclidean_distance(&a);
+            let diff = solution.placements[*k] - a;
+            let g = diff.mul(-(2.0 / (d * d * d * d)) * rate);
+            solution.placements[*k] += g;
+        }
+
+        let score = solution.score(input);
+        if let Ok(score) = score {
+            if score > best_score {
+                best_score = score;
+                best_solution = solution.clone();
+                eprintln!("iter {}, score: {}", i, best_score);
+            }
+        }
+        i += 1;
+    }
+    best_solution
+}
+
+pub fn random_exact(input: &Input, seed: u128, budget: &Budget) -> Result<Solution> {
+    let mut generator = PlacementGenerator::new(input, seed);
+
+    let mut best_score = Score::MIN;
+    let mut best_placements = vec![];
+    let mut iteration_count = 0;
+
+    while !budget.is_exhausted(iteration_count) {
+        iteration_count += 1;
+        let placements = generator.generate();
+        assert_eq!(placements.len(), input.musicians.len());
+
+        let mut matrix = Matrix::new(input.musicians.len(), placements.len(), OrderedFloat(0.0));
+        let mut reachable_placements = vec![];
+        for attendee in &input.attendees {
+            let non_blocked_placement_ids =
+                get_non_blocked_placement_ids(attendee.pos(), &placements);
+            reachable_placements.push(non_blocked_placement_ids);
+        }
+        for musician_id in 0..input.musicians.len() {
+            for (attendee_id, reachable) in reachable_placements.iter().enumerate() {
+                for &reachable_placement_id in reachable {
+                    // musician_id を placement_id に対応させたときの attendee_id に対応するスコアを計算
+                    let score = input.raw_impact(
+                        attendee_id,
+                        musician_id,
+                        &placements[reachable_placement_id],
+                    );
+                    matrix[(musician_id, reachable_placement_id)] += score;
+                }
+            }
+        }
+
+        let (_, assignments) = kuhn_munkres(&matrix);
+        let mut new_placements = vec![];
+        for assignment in assignments {
+            new_placements.push(placements[assignment]);
+        }
+        let solution = Solution {
+            placements: new_placements.clone(),
+            ..Default::default()
+        };
+        let new_score = solution.score(input)?;
+
+        if best_score < new_score {
+            eprintln!(
+                "Improved global score (iteration = {}): {} -> {}",
+                iteration_count, best_score, new_score
+            );
+            best_score = new_score;
+            best_placements = new_placements.clone();
+        }
+    }
+
+    if best_placements.is_empty() {
+        bail!("stopped before the first placement");
+    }
+    Ok(Solution {
+        placements: best_placements,
+        ..Default::default()
+    })
+}
+
+// 割当問題で使える格子点の数
+pub fn grid_size(input: &Input) -> (usize, usize) {
+    let x_count = ((input.stage_width / 10.0).floor() as usize).saturating_sub(1);
+    let y_count = ((input.stage_height / 10.0).floor() as usize).saturating_sub(1);
+    (x_count, y_count)
+}
+
+fn generate_first_level_candidates(input: &Input) -> Vec<Point> {
+    let (x_count, y_count) = grid_size(input);
+
+    // Generate first level candidate
+    let x_gap = if x_count > 1 {
+        (input.stage_width - 20.0) / (x_count - 1) as f64
+    } else {
+        0.0
+    };
+    let y_gap = if y_count > 1 {
+        (input.stage_height - 20.0) / (y_count - 1) as f64
+    } else {
+        0.0
+    };
+
+    // Prefer candidates closer to the stage borders
+    let mut layered_candidates = vec![];
+    for i in 0..x_count {
+        for j in 0..y_count {
+            let offset = Point::new(10.0 + i as f64 * x_gap, 10.0 + j as f64 * y_gap);
+            let pos = input.stage_bottom_left + offset;
+            let x_level = i.min(x_count - 1 - i);
+            let y_level = j.min(y_count - 1 - j);
+            let level = x_level.min(y_level);
+            while level + 1 > layered_candidates.len() {
+                layered_candidates.push(vec![]);
+            }
+            layered_candidates[level].push(pos);
+        }
+    }
+
+    let mut candidates = vec![];
+    for layer in layered_candidates {
+        candidates.extend(layer);
+        if candidates.len() >= input.musicians.len() {
+            break;
+        }
+    }
+    candidates
+}
+
+fn create_matching_matrix(input: &Input, candidates: &[Point]) -> Matrix<OrderedFloat<f64>> {
+    let mut matrix = Matrix::new(input.musicians.len(), candidates.len(), OrderedFloat(0.0));
+    let mut reachable_candidates = vec![];
+    for attendee in &input.attendees {
+        let attendee_pos = attendee.pos();
+        let non_blocked_candidate_ids = get_non_blocked_placement_ids(attendee_pos, candidates);
+        let candidate_ids = filter_placements_blocked_by_pillars(
+            attendee_pos,
+            candidates,
+            input.active_pillars(),
+            &non_blocked_candidate_ids,
+        );
+        reachable_candidates.push(candidate_ids);
+    }
+
+    for musician_id in 0..input.musicians.len() {
+        for (attendee_id, reachable) in reachable_candidates.iter().enumerate() {
+            for &reachable_candidate_id in reachable {
+                // musician_id を placement_id に対応させたときの attendee_id に対応するスコアを計算
+                let score = input.raw_impact(
+                    attendee_id,
+                    musician_id,
+                    &candidates[reachable_candidate_id],
+                );
+                matrix[(musician_id, reachable_candidate_id)] += score;
+            }
+        }
+    }
+    matrix
+}
+
+// 負の影響しかない組は音量0で置く前提で0にし、それ以外は音量10倍で評価する
+fn exact_match_candidates_with_volumes(
+    input: &Input,
+    candidates: &[Point],
+) -> (f64, Vec<Point>, Vec<f64>) {
+    let mut matrix = create_matching_matrix(input, candidates);
+    for musician_id in 0..input.musicians.len() {
+        for candidate_id in 0..candidates.len() {
+            let e = matrix[(musician_id, candidate_id)].0;
+            if e < 0.0 {
+                matrix[(musician_id, candidate_id)] = OrderedFloat(0.0);
+            } else {
+                matrix[(musician_id, candidate_id)] *= OrderedFloat(10.0);
+            }
+        }
+    }
+
+    let (score, assignments) = kuhn_munkres(&matrix);
+    let mut filtered_candidates = vec![];
+    let mut volumes = vec![];
+    for assignment_id in 0..assignments.len() {
+        let assignment = assignments[assignment_id];
+        filtered_candidates.push(candidates[assignment]);
+        if matrix[(assignment_id, assignment)].0 == 0.0 {
+            volumes.push(0.0);
+        } else {
+            volumes.push(10.0);
+        }
+    }
+
+    (score.0, filtered_candidates, volumes)
+}
+
+fn exact_match_candidates(input: &Input, candidates: &[Point]) -> (f64, Vec<Point>) {
+    let matrix = create_matching_matrix(input, candidates);
+    let (score, assignments) = kuhn_munkres(&matrix);
+    let mut filtered_candidates = vec![];
+    for assignment in assignments {
+        filtered_candidates.push(candidates[assignment]);
+    }
+    (score.0, filtered_candidates)
+}
+
+pub fn random_exact_rect(input: &Input, seed: u128) -> Solution {
+    let (x_count, y_count) = grid_size(input);
+    if x_count * y_count < input.musicians.len() {
+        // Give up
+        let mut generator = PlacementGenerator::new(input, seed);
+        return Solution::with_volumes(
+            generator.generate(),
+            Some(vec![10.0; input.musicians.len()]),
+        );
+    }
+
+    let first_level_candidates = generate_first_level_candidates(input);
+    // 最初はmusicianよりも多い候補地を用いて最適化を行い、その結果に基づき二段階目の最適化に使用する候補地を列挙
+    let (first_level_score, second_level_candidates, _) =
+        exact_match_candidates_with_volumes(input, &first_level_candidates);
+    eprintln!("first level score: {}", first_level_score);
+    let (second_level_score, best_placements, best_volumes) =
+        exact_match_candidates_with_volumes(input, &second_level_candidates);
+    eprintln!("second level score: {}", second_level_score);
+    Solution::with_volumes(best_placements, Some(best_volumes))
+}
+
+fn two_stage_optimization(input: &Input) -> Vec<Point> {
+    let first_level_candidates = generate_first_level_candidates(input);
+    // 最初はmusicianよりも多い候補地を用いて最適化を行い、その結果に基づき二段階目の最適化に使用する候補地を列挙
+    let (first_level_score, second_level_candidates) =
+        exact_match_candidates(input, &first_level_candidates);
+    eprintln!("first level score: {}", first_level_score);
+    let (second_level_score, best_placements) =
+        exact_match_candidates(input, &second_level_candidates);
+    eprintln!("second level score: {}", second_level_score);
+    best_placements
+}
+
+// 楽器の違う2人の入れ替えで良くなる限り続ける。差分はIncrementalScorerで正確に計算する
+fn hill_climbing(input: &Input, placements: &[Point], budget: &Budget) -> Vec<Point> {
+    let mut scorer = IncrementalScorer::new(
+        input,
+        &Solution {
+            placements: placements.to_vec(),
+            ..Default::default()
+        },
+    );
+    let mut score = scorer.score();
+    eprintln!("Started hill-climbing: score = {}", score);
+
+    let n = placements.len();
+    let mut has_update = true;
+    let mut round = 0;
+    'search: while has_update && !budget.is_exhausted(round) {
+        round += 1;
+        has_update = false;
+        for musician_i in 0..n {
+            for musician_j in 0..n {
+                if input.musicians[musician_i] == input.musicians[musician_j] {
+                    continue;
+                }
+                if budget.is_exhausted(round) {
+                    break 'search;
+                }
+                scorer.swap_musicians(musician_i, musician_j);
+                let new_score = scorer.score();
+                if new_score > score {
+                    score = new_score;
+                    scorer.commit();
+                    has_update = true;
+                } else {
+                    scorer.undo();
+                }
+            }
+
+            if has_update {
+                break;
+            }
+        }
+    }
+    eprintln!(
+        "Finished hill-climbing: score = {}, elapsed-time = {}",
+        score,
+        budget.elapsed().as_secs_f64()
+    );
+    scorer.placements().to_vec()
+}
+
+pub fn two_stage_exact_together(input: &Input, seed: u128, budget: &Budget) -> Solution {
+    let (x_count, y_count) = grid_size(input);
+    let placements = if x_count * y_count < input.musicians.len() {
+        // Give up
+        let mut generator = PlacementGenerator::new(input, seed);
+        generator.generate()
+    } else {
+        let base_placements = two_stage_optimization(input);
+        if input.rules.playing_together {
+            hill_climbing(input, &base_placements, budget)
+        } else {
+            base_placements
+        }
+    };
+    Solution {
+        placements,
+        ..Default::default()
+    }
+}
+
+const PICK_POINTS_COUNT: usize = 50;
+
+pub fn garasubo(input: &Input, seed: u128, budget: &Budget) -> Solution {
+    let mut solution: Solution = Default::default();
+
+    let mut instruments = HashMap::new();
+    for &m in &input.musicians {
+        let count = instruments.get(&m).unwrap_or(&0);
+        instruments.insert(m, count + 1);
+    }
+    let mut musician_map = vec![Vec::new(); instruments.keys().len()];
+    for (i, &m) in input.musicians.iter().enumerate() {
+        musician_map[m].push(i);
+    }
+
+    // 各楽器のそれっぽい人気度を計算
+    let mut popularity = Vec::with_capacity(instruments.keys().len());
+    let stage_center_x = input.stage_bottom_left.x() + input.stage_width / 2.0;
+    let stage_center_y = input.stage_bottom_left.y() + input.stage_height / 2.0;
+    let stage_center = Point::new(stage_center_x, stage_center_y);
+    let visible_attendees = input.get_visible_attendees(stage_center, &[]);
+    for i in 0..instruments.keys().len() {
+        popularity.push((
+            input.raw_score_for_instrument(stage_center, i, &visible_attendees),
+            i,
+        ));
+    }
+    let popularity_clone = popularity.clone();
+    // 人気度が高い楽器順のIDリスト生成
+    popularity.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap());
+    let instruments_ids = popularity.iter().map(|(_, i)| *i).collect::<Vec<_>>();
+
+    // musicianを置く候補となる地点を生成
+    let candidates = PlacementGenerator::honeycomb_candidates(input);
+    let mut candidates_graph = vec![vec![]; candidates.len()];
+    for i in 0..candidates.len() {
+        for j in i + 1..candidates.len() {
+            if candidates[i].euclidean_distance(&candidates[j]) < 10.0 + 1e-4 {
+                candidates_graph[i].push(j);
+                candidates_graph[j].push(i);
+            }
+        }
+    }
+
+    let mut best_score = Score::MIN;
+    let mut rnd = Pcg64Mcg::new(seed);
+    let mut best_solution = solution.clone();
+    let mut i = 0;
+    while !budget.is_exhausted(i) {
+        i += 1;
+        let mut available_points: BTreeSet<usize> = BTreeSet::from_iter(0..candidates.len());
+        let mut used = BTreeSet::new();
+        let mut current_solution = vec![];
+        let mut current_solution_mid = vec![];
+        // 楽器順に配置
+        'inst_loop: for &instrument_id in instruments_ids.iter() {
+            let mut count = 0;
+            while count < instruments[&instrument_id] {
+                let mut neighbors = BTreeSet::new();
+                if available_points.is_empty() {
+                    break 'inst_loop;
+                }
+
+                // ステージ上の候補地点からランダムに良さそうな箇所を選ぶ
+                let mut best_point = *available_points.iter().choose(&mut rnd).unwrap();
+                let grid = input.blocker_grid(&current_solution);
+                let tmp_visible_attendees =
+                    input.get_visible_attendees_with(&grid, candidates[best_point]);
+                let mut best_score = input.raw_score_for_instrument(
+                    candidates[best_point],
+                    instrument_id,
+                    &tmp_visible_attendees,
+                );
+                for _ in 0..PICK_POINTS_COUNT * 10 {
+                    let point = *available_points.iter().choose(&mut rnd).unwrap();
+                    let tmp_visible_attendees =
+                        input.get_visible_attendees_with(&grid, candidates[point]);
+                    let score = input.raw_score_for_instrument(
+                        candidates[point],
+                        instrument_id,
+                        &tmp_visible_attendees,
+                    );
+                    if score > best_score {
+                        best_score = score;
+                        best_point = point;
+                    }
+                }
+                current_solution.push(candidates[best_point]);
+                current_solution_mid.push(musician_map[instrument_id][count]);
+                used.insert(best_point);
+                available_points.remove(&best_point);
+
+                count += 1;
+                for &p in &candidates_graph[best_point] {
+                    if used.contains(&p) {
+                        continue;
+                    }
+                    neighbors.insert(p);
+                }
+                while count < instruments[&instrument_id] {
+                    if neighbors.is_empty() {
+                        eprintln!("couldn't find neighbor");
+                        break;
+                    }
+                    let pick_count = std::cmp::min(PICK_POINTS_COUNT, neighbors.len());
+                    let grid = input.blocker_grid(&current_solution);
+                    let (best_point, _) = neighbors
+                        .iter()
+                        .choose_multiple(&mut rnd, pick_count)
+                        .into_par_iter()
+                        .map(|&point| {
+                            let tmp_visible_attendees =
+                                input.get_visible_attendees_with(&grid, candidates[point]);
+                            let score = input.raw_score_for_instrument(
+                                candidates[point],
+                                instrument_id,
+                                &tmp_visible_attendees,
+                            );
+                            (point, score)
+                        })
+                        .max_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap())
+                        .unwrap();
+                    current_solution.push(candidates[best_point]);
+                    current_solution_mid.push(musician_map[instrument_id][count]);
+                    used.insert(best_point);
+                    available_points.remove(&best_point);
+                    neighbors.remove(&best_point);
+
+                    count += 1;
+                    for &p in &candidates_graph[best_point] {
+                        if used.contains(&p) {
+                            continue;
+                        }
+                        neighbors.insert(p);
+                    }
+                }
+            }
+        }
+        if current_solution.len() < input.musicians.len() {
+            eprintln!("couldn't find solution");
+            continue;
+        }
+        solution.placements = vec![Point::new(0.0, 0.0); input.musicians.len()];
+        let mut volumes = vec![0.0; input.musicians.len()];
+        for i in 0..current_solution.len() {
+            let mid = current_solution_mid[i];
+            let inst = input.musicians[mid];
+            solution.placements[mid] = current_solution[i];
+            if popularity_clone[inst].0 > 0.0 {
+                volumes[mid] = 10.0;
+            } else {
+                volumes[mid] = 0.0;
+            }
+        }
+        solution.volumes = Some(volumes);
+
+        match solution.score(input) {
+            Ok(score) => {
+                if score > best_score {
+                    best_score = score;
+                    best_solution = solution.clone();
+                    eprintln!("iter {}, score: {}", i, best_score);
+                }
+            }
+            Err(e) => {
+                eprintln!("iter {} error exit: {:?}", i, e);
+            }
+        }
+    }
+    if best_solution.placements.is_empty() {
+        return best_solution;
+    }
+    volume_optimize(input, &best_solution)
+}
+
+// 盾を動かす近傍とvolumeの切り替えだけで山登りする
+// volumeのルールがなければ盾は作れないので、random_exact_rectの配置をそのまま返す
+pub fn shield(input: &Input, seed: u128, budget: &Budget) -> Solution {
+    let base = random_exact_rect(input, seed);
+    if !input.rules.volumes {
+        return base;
+    }
+    let mut scorer = IncrementalScorer::new(input, &base);
+    let mut annealer = Annealer::new(Config {
+        acceptance: Acceptance::Greedy,
+        schedule: Schedule::Constant(0.0),
+        selection: Selection::ADAPTIVE,
+    })
+    .with_move(ShieldMove, 2.0)
+    .with_move(VolumeMove, 1.0);
+    let (_, solution) = annealer.run(&mut scorer, &mut Pcg64Mcg::new(seed), budget);
+    eprintln!("{}", annealer.stats());
+    solution
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_strategy() {
+        for strategy in Strategy::ALL {
+            assert_eq!(strategy.name().parse::<Strategy>().unwrap(), strategy);
+        }
+        assert!("two_stage".parse::<Strategy>().is_err());
+    }
+
+    #[test]
+    fn strategies_make_valid_solutions() {
+        let input = crate::read_input("./testdata/problem-29.json", None).unwrap();
+        for strategy in [
+            Strategy::Random,
+            Strategy::RandomExact,
+            Strategy::RandomExactRect,
+            Strategy::TwoStageExactTogether,
+        ] {
+            let solution = solve(&input, strategy, 0, &Budget::iterations(2)).unwrap();
+            assert!(input.check_solution(&solution).is_ok(), "{strategy}");
+        }
+    }
+
+    #[test]
+    fn hill_climbing_never_makes_it_worse() {
+        let mut rng = Pcg64Mcg::new(5);
+        for _ in 0..10 {
+            let case = crate::cross_check::random_case(&mut rng, ScoringRules::FULL);
+            let input = &case.input;
+            let placements = case.solution.placements.clone();
+            let initial = Solution {
+                placements: placements.clone(),
+                ..Default::default()
+            };
+            let climbed = Solution {
+                placements: hill_climbing(input, &placements, &Budget::iterations(5)),
+                ..Default::default()
+            };
+            assert!(input.check_solution(&climbed).is_ok());
+            assert!(input.score_fast(&climbed).unwrap() >= input.score_fast(&initial).unwrap());
+        }
+    }
+
+    #[test]
+    fn shield_never_makes_it_worse() {
+        let mut rng = Pcg64Mcg::new(8);
+        for _ in 0..10 {
+            let case = crate::cross_check::random_case(&mut rng, ScoringRules::FULL);
+            let input = &case.input;
+            let base = random_exact_rect(input, 0);
+            let solution = solve(input, Strategy::Shield, 0, &Budget::iterations(200)).unwrap();
+            assert!(input.check_solution(&solution).is_ok());
+            assert!(input.score_fast(&solution).unwrap() >= input.score_fast(&base).unwrap());
+        }
+    }
+}
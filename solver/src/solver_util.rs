@@ -1,331 +1,83 @@
-use crate::{get_time, Pillar};
-use crate::problem::{Input, Segment, Solution};
+use crate::budget::Budget;
+use crate::incremental::IncrementalScorer;
+use crate::problem::{impact_score, Input, Score, Segment, Solution, DEFAULT_VOLUME, MAX_VOLUME};
 use geo::{EuclideanDistance, Point};
 use ordered_float::OrderedFloat;
 use rand::Rng;
 
-struct PlayTogetherIndex {
-    enabled: bool,
-    placements: Vec<Point>,
-    musicians: Vec<usize>,
-    play_together_scores: Vec<f64>,
-}
-
-impl PlayTogetherIndex {
-    fn new(input: &Input, placements: &Vec<Point>) -> Self {
-        let mut play_together_scores = vec![1.0; placements.len()];
-
-        if input.pillars.len() > 0 {
-            for musician_i in 0..placements.len() {
-                for musician_j in musician_i + 1..placements.len() {
-                    if input.musicians[musician_i] == input.musicians[musician_j] {
-                        let dist = placements[musician_i].euclidean_distance(&placements[musician_j]);
-                        play_together_scores[musician_i] += 1.0 / dist;
-                        play_together_scores[musician_j] += 1.0 / dist;
-                    }
-                }
-            }
-        }
-
-        PlayTogetherIndex {
-            enabled: input.pillars.len() > 0,
-            placements: placements.clone(),
-            musicians: input.musicians.clone(),
-            play_together_scores,
-        }
-    }
-
-    fn get(&self, i: usize) -> f64 {
-        self.play_together_scores[i]
-    }
-
-    fn move_musician(&mut self, musician_i: usize, new_point: Point) {
-        if !self.enabled {
-            return;
-        }
-
-        let old_point = self.placements[musician_i];
-
-        self.play_together_scores[musician_i] = 1.0;
-        for musician_j in 0..self.placements.len() {
-            if musician_i != musician_j && self.musicians[musician_i] == self.musicians[musician_j] {
-                let old_dist = old_point.euclidean_distance(&self.placements[musician_j]);
-                let new_dist = new_point.euclidean_distance(&self.placements[musician_j]);
-                self.play_together_scores[musician_j] -= 1.0 / old_dist;
-                self.play_together_scores[musician_j] += 1.0 / new_dist;
-                self.play_together_scores[musician_i] += 1.0 / new_dist;
-            }
-        }
-        self.placements[musician_i] = new_point;
-    }
-}
-
-struct AttendeeIndex {
-    musician_point: Point,
-    tastes: Vec<f64>,
-    sorted_angles: Vec<(OrderedFloat<f64>, usize)>,
-    attendee_points: Vec<Point>,
-    impact: f64,
-    cover_counts: Vec<i32>,
-}
-
-impl AttendeeIndex {
-    fn create(musician_id: usize, musician_point: Point, input: &Input) -> Self {
-        let mut tastes = vec![];
-        // let mut angles = vec![];
-        let mut impact = 0.0;
-        let instrument_id = input.musicians[musician_id];
-
-        let mut sorted_angles = vec![];
-        let mut attendee_points = vec![];
-        for i in 0..input.attendees.len() {
-            let taste = input.attendees[i].tastes[instrument_id];
-            let attendee_point = input.attendees[i].pos();
-            let distance = attendee_point.euclidean_distance(&musician_point);
-            let taste = (1000000.0 * taste / (distance * distance)).ceil();
-            tastes.push(taste);
-            impact += taste;
-
-            let dx = input.attendees[i].x - musician_point.x();
-            let dy = input.attendees[i].y - musician_point.y();
-            let angle = dy.atan2(dx);
-            // angles.push(angle);
-
-            sorted_angles.push((OrderedFloat(angle) - 2.0 * std::f64::consts::PI, i));
-            sorted_angles.push((OrderedFloat(angle), i));
-            sorted_angles.push((OrderedFloat(angle) + 2.0 * std::f64::consts::PI, i));
-            attendee_points.push(attendee_point);
-        }
-        // 番兵
-        sorted_angles.push((OrderedFloat(-4.0 * std::f64::consts::PI), 0));
-        sorted_angles.push((OrderedFloat(4.0 * std::f64::consts::PI), 0));
-        sorted_angles.sort();
-
-        AttendeeIndex {
-            musician_point,
-            tastes,
-            sorted_angles,
-            attendee_points,
-            impact,
-            cover_counts: vec![0; input.attendees.len()],
-        }
-    }
-
-    fn decrease(&mut self, point: Point) {
-        self.add(point, 5.0, -1, false);
-    }
-
-    fn increase(&mut self, point: Point) {
-        self.add(point, 5.0, 1, false)
-    }
-
-    fn add_pillar(&mut self, pillar: &Pillar) {
-        self.add(pillar.center, pillar.radius, 1, true);
-    }
+pub fn yamanobori(
+    input: &Input,
+    best: &mut Vec<Point>,
+    best_volume: &[f64],
+    budget: &Budget,
+    rand_seed: u128,
+    reduce_num: usize,
+) -> Solution {
+    let mut rng = rand_pcg::Pcg64Mcg::new(rand_seed);
+    let input = reduce_attendees(input, reduce_num);
+    // 受理したらcommit、棄却したらundoで戻す
+    let mut scorer = IncrementalScorer::new(
+        &input,
+        &Solution::with_volumes(best.clone(), Some(best_volume.to_owned())),
+    );
+    let mut best_score = scorer.score();
+    let mut count = 0;
 
-    fn add(&mut self, point: Point, radius: f64, value: i32, need_check: bool) {
-        let dx = point.x() - self.musician_point.x();
-        let dy = point.y() - self.musician_point.y();
-        let distance = point.euclidean_distance(&self.musician_point);
-        let angle = dy.atan2(dx);
-        let asin = (radius / distance).asin();
-        let (angle_left, angle_right) = (OrderedFloat(angle - asin), OrderedFloat(angle + asin));
+    while !budget.is_exhausted(count) {
+        count += 1;
 
-        let mut lb = 0;
-        let mut ub = self.sorted_angles.len();
-        while ub - lb > 1 {
-            let mb = (ub + lb) / 2;
-            if self.sorted_angles[mb].0 <= angle_left {
-                lb = mb;
+        // 位置の移動に混ぜてvolumeを0と10で切り替える
+        if input.rules.volumes && rng.gen_range(0..10) == 0 {
+            let idx = rng.gen_range(0..best.len());
+            let new_volume = if scorer.volume(idx) < 1.1 {
+                MAX_VOLUME
             } else {
-                ub = mb;
-            }
-        }
-        let mut cursor = ub;
-        while cursor < self.sorted_angles.len() && self.sorted_angles[cursor].0 < angle_right {
-            let (_, index) = self.sorted_angles[cursor];
-            let hit = if need_check {
-                let segment = Segment {
-                    p1: self.musician_point,
-                    p2: self.attendee_points[index]
-                };
-                segment.dist(&point) < radius
-            } else {
-                true
+                0.0
             };
-
-            if hit {
-                let current = self.cover_counts[index];
-                self.cover_counts[index] += value;
-                assert!(self.cover_counts[index] >= 0);
-
-                if current == 0 && self.cover_counts[index] > 0 {
-                    self.impact -= self.tastes[index];
-                }
-
-                if current > 0 && self.cover_counts[index] == 0 {
-                    self.impact += self.tastes[index];
-                }
-            }
-            cursor += 1;
-        }
-    }
-
-    fn get_impact(&self) -> f64 {
-        self.impact
-    }
-}
-
-struct ImpactIndex {
-    input: Input,
-    placements: Vec<Point>,
-    attendee_indexes: Vec<AttendeeIndex>,
-}
-
-impl ImpactIndex {
-    fn new(input: &Input, placements: &Vec<Point>) -> Self {
-        let mut attendee_indexes = vec![];
-        for musician_i in 0..input.musicians.len() {
-            let mut attendee_index =
-                AttendeeIndex::create(musician_i, placements[musician_i], input);
-            for musician_j in 0..input.musicians.len() {
-                if musician_i != musician_j {
-                    attendee_index.increase(placements[musician_j]);
-                }
-            }
-            for pillar in &input.pillars {
-                attendee_index.add_pillar(pillar);
-            }
-
-            attendee_indexes.push(attendee_index);
-        }
-        ImpactIndex {
-            input: input.clone(),
-            placements: placements.clone(),
-            attendee_indexes,
-        }
-    }
-
-    fn get(&self, i: usize) -> f64 {
-        self.attendee_indexes[i].get_impact()
-    }
-
-    fn move_musician(&mut self, musician_i: usize, new_point: Point) {
-        let old_point = self.placements[musician_i];
-        for musician_j in 0..self.input.musicians.len() {
-            if musician_i == musician_j {
-                continue;
-            }
-            self.attendee_indexes[musician_j].decrease(old_point);
-            self.attendee_indexes[musician_j].increase(new_point);
-        }
-        self.placements[musician_i] = new_point;
-        self.attendee_indexes[musician_i] =
-            AttendeeIndex::create(musician_i, new_point, &self.input);
-        for musician_j in 0..self.input.musicians.len() {
-            if musician_i != musician_j {
-                self.attendee_indexes[musician_i].increase(self.placements[musician_j]);
+            scorer.set_volume(idx, new_volume);
+            let sc = scorer.score();
+            if sc > best_score {
+                eprintln!(
+                    "score for reduced attendees is improved by volume (time = {}, count = {}): {} -> {}",
+                    budget.elapsed().as_secs_f64(),
+                    count,
+                    best_score,
+                    sc,
+                );
+                best_score = sc;
+                scorer.commit();
+            } else {
+                scorer.undo();
             }
-        }
-        for pillar in &self.input.pillars {
-            self.attendee_indexes[musician_i].add_pillar(pillar);
-        }
-    }
-}
-
-struct ScoringIndex {
-    input: Input,
-    volumes: Vec<f64>,
-    play_together_index: PlayTogetherIndex,
-    impact_index: ImpactIndex,
-}
-
-impl ScoringIndex {
-    fn new(input: &Input, placements: &Vec<Point>, volumes: &Vec<f64>) -> Self {
-        ScoringIndex {
-            input: input.clone(),
-            // placements: placements.clone(),
-            volumes: volumes.clone(),
-            // cover_counts,
-            play_together_index: PlayTogetherIndex::new(input, placements),
-            impact_index: ImpactIndex::new(input, placements),
-        }
-    }
-
-    fn get_score(&self) -> f64 {
-        let mut score = 0.0;
-        for i in 0..self.input.musicians.len() {
-            score += self.volumes[i] * self.play_together_index.get(i) * self.impact_index.get(i);
-        }
-        score
-    }
-
-    fn is_valid_move(&self, musician_i: usize, new_point: Point) -> bool {
-        // Check musician is in stage
-        if !self.input.in_stage(&new_point) {
-            return false;
-        }
-
-        // Check distance from room walls
-        const MUSICIAN_CLOSE_DIST: f64 = 10.0;
-        if !((MUSICIAN_CLOSE_DIST <= new_point.x()
-            && new_point.x() <= self.input.room_width - MUSICIAN_CLOSE_DIST)
-            && (MUSICIAN_CLOSE_DIST <= new_point.y()
-                && new_point.y() <= self.input.room_height - MUSICIAN_CLOSE_DIST))
-        {
-            return false;
+            continue;
         }
 
-        // Check ditances between musicians
-        for musician_j in 0..self.input.musicians.len() {
-            if musician_i == musician_j {
+        // 楽器の違う2人の位置を入れ替える
+        if rng.gen_range(0..10) == 0 {
+            let i = rng.gen_range(0..best.len());
+            let j = rng.gen_range(0..best.len());
+            if input.musicians[i] == input.musicians[j] {
                 continue;
             }
-
-            let dist = new_point.euclidean_distance(&self.impact_index.placements[musician_j]);
-            if dist < MUSICIAN_CLOSE_DIST {
-                return false;
+            scorer.swap_musicians(i, j);
+            let sc = scorer.score();
+            if sc > best_score {
+                eprintln!(
+                    "score for reduced attendees is improved by swap (time = {}, count = {}): {} -> {}",
+                    budget.elapsed().as_secs_f64(),
+                    count,
+                    best_score,
+                    sc,
+                );
+                best_score = sc;
+                best.swap(i, j);
+                scorer.commit();
+            } else {
+                scorer.undo();
             }
+            continue;
         }
-        true
-    }
-
-    fn move_musician(&mut self, musician_i: usize, new_point: Point) -> bool {
-        if self.is_valid_move(musician_i, new_point) {
-            self.play_together_index
-                .move_musician(musician_i, new_point);
-            self.impact_index.move_musician(musician_i, new_point);
-            true
-        } else {
-            false
-        }
-    }
-}
 
-pub fn yamanobori(
-    input: &Input,
-    best: &mut Vec<Point>,
-    best_volume: &[f64],
-    timeout: f64,
-    rand_seed: u128,
-    reduce_num: usize,
-) -> Vec<Point> {
-    let mut rng = rand_pcg::Pcg64Mcg::new(rand_seed);
-    let input = reduce_attendees(input, reduce_num);
-    let mut best_score = input
-        .score_fast(&Solution {
-            placements: best.clone(),
-            volumes: Some(best_volume.to_owned()),
-        })
-        .unwrap();
-
-    let mut scoring_index = ScoringIndex::new(&input, best, &best_volume.to_vec());
-    dbg!(best_score);
-    dbg!(scoring_index.get_score());
-    let mut count = 0;
-
-    while get_time() < timeout {
-        count += 1;
         let mut current = best.clone();
         let idx = rng.gen_range(0..best.len());
         let dir = rng.gen_range(0..4);
@@ -335,34 +87,27 @@ pub fn yamanobori(
         *current[idx].x_mut() += dx[dir] * step;
         *current[idx].y_mut() += dy[dir] * step;
 
-        // if input.is_valid_placements(&current).is_err() {
-        //     continue;
-        // }
-
-        let old_point = best[idx];
-        let new_point = current[idx];
-        let move_success = scoring_index.move_musician(idx, new_point);
-        if !move_success {
+        if !scorer.move_musician(idx, current[idx]) {
             continue;
         }
 
-        let sc = scoring_index.get_score();
+        let sc = scorer.score();
         if sc > best_score {
             eprintln!(
                 "score for reduced attendees is improved (time = {}, count = {}): {} -> {}",
-                get_time(),
+                budget.elapsed().as_secs_f64(),
                 count,
                 best_score,
                 sc,
             );
             best_score = sc;
             *best = current;
-            // dbg!(scoring_index.get_score());
+            scorer.commit();
         } else {
-            scoring_index.move_musician(idx, old_point);
+            scorer.undo();
         }
     }
-    best.to_vec()
+    scorer.solution()
 }
 
 pub fn reduce_attendees(input: &Input, num: usize) -> Input {
@@ -403,36 +148,29 @@ pub fn reduce_attendees(input: &Input, num: usize) -> Input {
 pub fn volume_optimize_fast(input: &Input, solution: &Solution) -> Solution {
     let mut solution = solution.clone();
     let mut best_score = solution.score(input).unwrap();
-
-    let original_volumes = solution
-        .volumes
-        .clone()
-        .unwrap_or(vec![1.0; input.musicians.len()]);
-    solution.volumes = Some(original_volumes);
+    solution.fill_default_volumes();
 
     // Volume optimize
     for i in 0..input.musicians.len() {
         let score = input.raw_score_for_musician(i, &solution.placements);
-        let tmp = solution.volumes.as_ref().map(|v| v[i]).unwrap_or(1.0);
-        if let Some(volumes) = &mut solution.volumes {
-            if score < 0.0 {
-                volumes[i] = 0.0;
-            } else {
-                volumes[i] = 10.0;
-            }
+        let tmp = solution.volume(i);
+        if score < 0.0 {
+            solution.set_volume(i, 0.0);
+        } else {
+            solution.set_volume(i, MAX_VOLUME);
         }
         match solution.score(input) {
             Ok(score) => {
                 if score > best_score {
                     best_score = score;
-                    println!("iter {}, score: {}", i, best_score);
+                    eprintln!("iter {}, score: {}", i, best_score);
                     continue;
-                } else if let Some(volumes) = &mut solution.volumes {
-                    volumes[i] = tmp;
+                } else {
+                    solution.set_volume(i, tmp);
                 }
             }
             Err(e) => {
-                println!("iter {} error exit: {:?}", i, e);
+                eprintln!("iter {} error exit: {:?}", i, e);
             }
         }
     }
@@ -442,35 +180,128 @@ pub fn volume_optimize_fast(input: &Input, solution: &Solution) -> Solution {
 pub fn volume_optimize(input: &Input, solution: &Solution) -> Solution {
     let mut solution = solution.clone();
     let mut best_score = solution.score(input).unwrap();
-
-    let original_volumes = solution
-        .volumes
-        .clone()
-        .unwrap_or(vec![1.0; input.musicians.len()]);
-    solution.volumes = Some(original_volumes);
+    solution.fill_default_volumes();
 
     // Volume optimize
     for i in 0..input.musicians.len() {
-        for vol in [0.0, 0.1, 9.9, 10.0] {
-            let tmp = solution.volumes.as_ref().map(|v| v[i]).unwrap_or(1.0);
-            if let Some(volumes) = &mut solution.volumes {
-                volumes[i] = vol;
-            }
+        for vol in [0.0, 0.1, MAX_VOLUME - 0.1, MAX_VOLUME] {
+            let tmp = solution.volume(i);
+            solution.set_volume(i, vol);
             match solution.score(input) {
                 Ok(score) => {
                     if score > best_score {
                         best_score = score;
-                        println!("iter {}, score: {}", i, best_score);
+                        eprintln!("iter {}, score: {}", i, best_score);
                         continue;
-                    } else if let Some(volumes) = &mut solution.volumes {
-                        volumes[i] = tmp;
+                    } else {
+                        solution.set_volume(i, tmp);
                     }
                 }
                 Err(e) => {
-                    println!("iter {} error exit: {:?}", i, e);
+                    eprintln!("iter {} error exit: {:?}", i, e);
                 }
             }
         }
     }
     solution
 }
+
+// 観客ごとに、好きな楽器から順に一番近い候補地にいると仮定したときのスコアの見積もり (nobishiro)
+// 遮蔽を無視するので、実際に置いたときのスコアの目安にしかならない
+pub fn nobishiro_estimate(input: &Input, sync_effect: bool) -> Score {
+    let candidates = crate::PlacementGenerator::honeycomb_candidates(input);
+    let mut score: Score = 0;
+    for attendee in &input.attendees {
+        let mut musicians = input.musicians.clone();
+        // 嗜好性でソート
+        musicians.sort_by(|a, b| {
+            attendee.tastes[*a]
+                .partial_cmp(&attendee.tastes[*b])
+                .unwrap()
+        });
+        // 昇順に並び替え
+        musicians.reverse();
+        let mut musician_candidates = candidates.clone();
+        // musician_candidatesを観客に近い順でソートする。
+        musician_candidates.sort_by(|a, b| {
+            let d1 = a.euclidean_distance(&attendee.pos());
+            let d2 = b.euclidean_distance(&attendee.pos());
+            d1.partial_cmp(&d2).unwrap()
+        });
+        musician_candidates.reverse();
+
+        // 音楽家の配置場所
+        let mut assignment = vec![];
+
+        // 音楽家の配置を決める
+        for musician in musicians {
+            let taste = attendee.tastes[musician];
+            if taste <= 0. {
+                break;
+            }
+            // 最も近い配置場所にいると仮定する。
+            let Some(pos) = musician_candidates.pop() else {
+                break;
+            };
+            assignment.push((musician, pos))
+        }
+
+        // スコア計算
+        let volume = if input.rules.volumes {
+            MAX_VOLUME
+        } else {
+            DEFAULT_VOLUME
+        };
+        for i in 0..assignment.len() {
+            let (musician, pos) = assignment[i];
+            let mut sync_effect_factor = 1.0;
+            if sync_effect || input.rules.playing_together {
+                for (j, &(other_musician, other_pos)) in assignment.iter().enumerate() {
+                    if i == j {
+                        continue;
+                    }
+                    if musician != other_musician {
+                        continue;
+                    }
+                    sync_effect_factor += 1.0 / pos.euclidean_distance(&other_pos);
+                }
+            }
+            let d = pos.euclidean_distance(&attendee.pos());
+            score += impact_score(
+                volume,
+                sync_effect_factor,
+                f64::ceil(1000000.0 * attendee.tastes[musician] / (d * d)),
+            );
+        }
+    }
+    score
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cross_check::random_case;
+    use crate::problem::ScoringRules;
+
+    #[test]
+    fn yamanobori_never_makes_it_worse() {
+        let mut rng = rand_pcg::Pcg64Mcg::new(3);
+        for _ in 0..10 {
+            let case = random_case(&mut rng, ScoringRules::FULL);
+            let input = &case.input;
+            let initial = input.score_fast(&case.solution).unwrap();
+            let mut placements = case.solution.placements.clone();
+            let solution = yamanobori(
+                input,
+                &mut placements,
+                &case.solution.volumes(),
+                &Budget::iterations(300),
+                0,
+                input.attendees.len(),
+            );
+            assert!(input.check_solution(&solution).is_ok());
+            assert_eq!(solution.placements, placements);
+            assert!(input.score_fast(&solution).unwrap() >= initial);
+        }
+    }
+}
@@ -0,0 +1,245 @@
+// 視線の遮蔽判定を速くするための一様グリッド
+use crate::problem::{Blocker, Pillar, Segment, BLOCKED_DIST};
+use geo::Point;
+
+// セル境界付近で取りこぼさないための余白
+const MARGIN: f64 = 1e-6;
+// 大きなpillarがあってもセル数が増えすぎないようにする
+const MAX_CELLS: usize = 1 << 20;
+
+// musicianとpillarを、円の外接矩形が重なるセルすべてに登録する
+// 線分が円と交わるなら交点を含むセルを線分が通るので、通るセルだけ調べればよい
+pub struct BlockerGrid {
+    origin: Point,
+    cell_size: f64,
+    cols: usize,
+    rows: usize,
+    // 0..musicians.len()はmusician、それ以降はpillar
+    cells: Vec<Vec<usize>>,
+    musicians: Vec<Point>,
+    pillars: Vec<Pillar>,
+}
+
+impl BlockerGrid {
+    pub fn new(placements: &[Point], pillars: &[Pillar]) -> Self {
+        let circles = placements
+            .iter()
+            .map(|&p| (p, BLOCKED_DIST))
+            .chain(pillars.iter().map(|p| (p.center, p.radius)))
+            .collect::<Vec<_>>();
+        let mut grid = BlockerGrid {
+            origin: Point::new(0.0, 0.0),
+            cell_size: 1.0,
+            cols: 0,
+            rows: 0,
+            cells: vec![],
+            musicians: placements.to_vec(),
+            pillars: pillars.to_vec(),
+        };
+        if circles.is_empty() {
+            return grid;
+        }
+
+        let (mut min_x, mut min_y) = (f64::INFINITY, f64::INFINITY);
+        let (mut max_x, mut max_y) = (f64::NEG_INFINITY, f64::NEG_INFINITY);
+        for &(c, r) in circles.iter() {
+            min_x = min_x.min(c.x() - r);
+            min_y = min_y.min(c.y() - r);
+            max_x = max_x.max(c.x() + r);
+            max_y = max_y.max(c.y() + r);
+        }
+        let width = max_x - min_x + 2.0 * MARGIN;
+        let height = max_y - min_y + 2.0 * MARGIN;
+        // 1セルに1つ程度になる大きさ。musicianは10以上離れているので2 * BLOCKED_DISTを下限にする
+        let mut cell_size = (width * height / circles.len() as f64)
+            .sqrt()
+            .max(2.0 * BLOCKED_DIST);
+        while ((width / cell_size).ceil() * (height / cell_size).ceil()) as usize > MAX_CELLS {
+            cell_size *= 2.0;
+        }
+        grid.origin = Point::new(min_x - MARGIN, min_y - MARGIN);
+        grid.cell_size = cell_size;
+        grid.cols = ((width / cell_size).ceil() as usize).max(1);
+        grid.rows = ((height / cell_size).ceil() as usize).max(1);
+        grid.cells = vec![vec![]; grid.cols * grid.rows];
+
+        for (id, &(c, r)) in circles.iter().enumerate() {
+            let (c0, c1) = grid.col_range(c.x() - r - MARGIN, c.x() + r + MARGIN);
+            let (r0, r1) = grid.row_range(c.y() - r - MARGIN, c.y() + r + MARGIN);
+            for col in c0..=c1 {
+                for row in r0..=r1 {
+                    grid.cells[row * grid.cols + col].push(id);
+                }
+            }
+        }
+        grid
+    }
+
+    fn col_range(&self, x0: f64, x1: f64) -> (usize, usize) {
+        let to_col = |x: f64| {
+            (((x - self.origin.x()) / self.cell_size).floor().max(0.0) as usize).min(self.cols - 1)
+        };
+        (to_col(x0), to_col(x1))
+    }
+
+    fn row_range(&self, y0: f64, y1: f64) -> (usize, usize) {
+        let to_row = |y: f64| {
+            (((y - self.origin.y()) / self.cell_size).floor().max(0.0) as usize).min(self.rows - 1)
+        };
+        (to_row(y0), to_row(y1))
+    }
+
+    // 線分p1-p2が通るセルに登録されたものを列挙する (重複あり)。fがtrueを返したら打ち切る
+    fn visit(&self, p1: Point, p2: Point, mut f: impl FnMut(usize) -> bool) {
+        if self.cells.is_empty() {
+            return;
+        }
+        let (lo_x, hi_x) = (p1.x().min(p2.x()), p1.x().max(p2.x()));
+        let grid_max_x = self.origin.x() + self.cols as f64 * self.cell_size;
+        let grid_max_y = self.origin.y() + self.rows as f64 * self.cell_size;
+        if hi_x < self.origin.x() || lo_x > grid_max_x {
+            return;
+        }
+        if p1.y().max(p2.y()) < self.origin.y() || p1.y().min(p2.y()) > grid_max_y {
+            return;
+        }
+
+        let dx = p2.x() - p1.x();
+        let dy = p2.y() - p1.y();
+        let (c0, c1) = self.col_range(lo_x, hi_x);
+        for col in c0..=c1 {
+            // このセル列の中での線分のyの範囲
+            let x0 = (self.origin.x() + col as f64 * self.cell_size).max(lo_x);
+            let x1 = (self.origin.x() + (col + 1) as f64 * self.cell_size).min(hi_x);
+            let (y0, y1) = if dx == 0.0 {
+                (p1.y(), p2.y())
+            } else {
+                (
+                    p1.y() + (x0 - p1.x()) * dy / dx,
+                    p1.y() + (x1 - p1.x()) * dy / dx,
+                )
+            };
+            if y0.max(y1) + MARGIN < self.origin.y() || y0.min(y1) - MARGIN > grid_max_y {
+                continue;
+            }
+            let (r0, r1) = self.row_range(y0.min(y1) - MARGIN, y0.max(y1) + MARGIN);
+            for row in r0..=r1 {
+                for &id in self.cells[row * self.cols + col].iter() {
+                    if f(id) {
+                        return;
+                    }
+                }
+            }
+        }
+    }
+
+    fn blocks(&self, segment: Segment, id: usize, skip: Option<usize>) -> bool {
+        if id < self.musicians.len() {
+            Some(id) != skip && segment.dist(&self.musicians[id]) < BLOCKED_DIST
+        } else {
+            let pillar = &self.pillars[id - self.musicians.len()];
+            segment.dist(&pillar.center) < pillar.radius
+        }
+    }
+
+    // Input::find_blockerと同じく、pillarを優先して番号が最小のものを返す
+    // skipはp2にいるmusician自身
+    pub fn find_blocker(&self, p1: Point, p2: Point, skip: Option<usize>) -> Option<Blocker> {
+        let segment = Segment { p1, p2 };
+        let mut min_id = usize::MAX;
+        let mut min_pillar_id = usize::MAX;
+        self.visit(p1, p2, |id| {
+            let ordered = if id < self.musicians.len() {
+                id < min_id
+            } else {
+                id < min_pillar_id
+            };
+            if ordered && self.blocks(segment, id, skip) {
+                if id < self.musicians.len() {
+                    min_id = id;
+                } else {
+                    min_pillar_id = id;
+                }
+            }
+            false
+        });
+        if min_pillar_id != usize::MAX {
+            Some(Blocker::Pillar(min_pillar_id - self.musicians.len()))
+        } else if min_id != usize::MAX {
+            Some(Blocker::Musician(min_id))
+        } else {
+            None
+        }
+    }
+
+    pub fn is_blocked(&self, p1: Point, p2: Point, skip: Option<usize>) -> bool {
+        let segment = Segment { p1, p2 };
+        let mut blocked = false;
+        self.visit(p1, p2, |id| {
+            blocked = self.blocks(segment, id, skip);
+            blocked
+        });
+        blocked
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cross_check::random_case;
+    use crate::problem::ScoringRules;
+    use rand::Rng;
+    use rand_pcg::Pcg64Mcg;
+
+    #[test]
+    fn grid_matches_find_blocker() {
+        let mut rng = Pcg64Mcg::new(7);
+        for _ in 0..300 {
+            let case = random_case(&mut rng, ScoringRules::FULL);
+            let input = &case.input;
+            let placements = &case.solution.placements;
+            let grid = input.blocker_grid(placements);
+            for attendee_id in 0..input.attendees.len() {
+                let a = input.attendees[attendee_id].pos();
+                for (m, &p) in placements.iter().enumerate() {
+                    let expected = input.find_blocker(attendee_id, m, placements);
+                    assert_eq!(grid.find_blocker(a, p, Some(m)), expected);
+                    assert_eq!(grid.is_blocked(a, p, Some(m)), expected.is_some());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn grid_with_far_segments() {
+        // 線分がグリッドの外から入ってくる場合
+        let mut rng = Pcg64Mcg::new(3);
+        let placements = (0..20)
+            .map(|i| Point::new(100.0 + 10.0 * (i % 5) as f64, 100.0 + 10.0 * (i / 5) as f64))
+            .collect::<Vec<_>>();
+        let pillars = vec![Pillar {
+            center: Point::new(500.0, 0.0),
+            radius: 30.0,
+        }];
+        let grid = BlockerGrid::new(&placements, &pillars);
+        for _ in 0..1000 {
+            let a = Point::new(
+                rng.gen_range(-1000.0..1000.0),
+                rng.gen_range(-1000.0..1000.0),
+            );
+            let m = rng.gen_range(0..placements.len());
+            let segment = Segment {
+                p1: a,
+                p2: placements[m],
+            };
+            let expected = if segment.dist(&pillars[0].center) < pillars[0].radius {
+                Some(Blocker::Pillar(0))
+            } else {
+                (0..placements.len())
+                    .find(|&i| i != m && segment.dist(&placements[i]) < BLOCKED_DIST)
+                    .map(Blocker::Musician)
+            };
+            assert_eq!(grid.find_blocker(a, placements[m], Some(m)), expected);
+        }
+    }
+}
@@ -0,0 +1,268 @@
+// 問題の特徴。戦略を選ぶときにその場で条件を書く代わりに、ここで計算した値を見る
+use crate::problem::*;
+use crate::solve::grid_size;
+use crate::PlacementGenerator;
+use geo::{EuclideanDistance, Line, Point};
+use serde::{Deserialize, Serialize};
+
+// ステージからこの距離までにいる観客を数える
+pub const NEAR_DISTANCES: [f64; 3] = [50.0, 100.0, 200.0];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Capacity {
+    // 蜂の巣状に並べたときの席の数
+    pub honeycomb: usize,
+    // 20間隔の格子
+    pub cartesian: usize,
+    // random_exact_rectなどが使う、端に沿った10間隔の格子
+    pub grid: usize,
+    // musician数 / 蜂の巣の席数。1を超えると蜂の巣には並びきらない
+    pub fill: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Instruments {
+    pub count: usize,
+    // 楽器ごとのmusicianの数
+    pub multiplicities: Vec<usize>,
+    pub max_multiplicity: usize,
+    // 2人以上いる楽器の数。playing togetherが効く
+    pub duplicated: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Tastes {
+    pub positive: usize,
+    pub negative: usize,
+    pub zero: usize,
+    pub negative_ratio: f64,
+    // 負の嗜好を1つでも持つ観客の数
+    pub attendees_with_negative: usize,
+    pub min: f64,
+    pub max: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Pillars {
+    pub count: usize,
+    // 柱の面積の合計 / 部屋の面積 (重なりは気にしない)
+    pub area_ratio: f64,
+    // ステージの中心への視線が柱で遮られる観客の割合
+    pub blocked_attendee_ratio: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NearStage {
+    pub within: f64,
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Attendees {
+    pub count: usize,
+    // ステージの長方形までの距離
+    pub min_distance: f64,
+    pub median_distance: f64,
+    pub mean_distance: f64,
+    pub near_stage: Vec<NearStage>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProblemStats {
+    pub problem_id: Option<u32>,
+    pub rules: String,
+    pub room_width: f64,
+    pub room_height: f64,
+    pub stage_width: f64,
+    pub stage_height: f64,
+    pub stage_bottom_left: (f64, f64),
+    // ステージの面積 / 部屋の面積
+    pub stage_area_ratio: f64,
+    pub musicians: usize,
+    pub capacity: Capacity,
+    pub instruments: Instruments,
+    pub tastes: Tastes,
+    pub pillars: Pillars,
+    pub attendees: Attendees,
+    // Input::upper_bound。有限の上界がなければNone
+    pub upper_bound: Option<Score>,
+}
+
+fn distance_to_stage(input: &Input, p: Point) -> f64 {
+    let (left, bottom) = input.stage_bottom_left.x_y();
+    let dx = (left - p.x())
+        .max(p.x() - left - input.stage_width)
+        .max(0.0);
+    let dy = (bottom - p.y())
+        .max(p.y() - bottom - input.stage_height)
+        .max(0.0);
+    dx.hypot(dy)
+}
+
+impl ProblemStats {
+    pub fn new(problem_id: Option<u32>, input: &Input) -> Self {
+        let musicians = input.musicians.len();
+        let honeycomb = PlacementGenerator::honeycomb_candidates(input).len();
+        let (x_count, y_count) = grid_size(input);
+        let capacity = Capacity {
+            honeycomb,
+            cartesian: PlacementGenerator::cartesian_coordinate_candidates(input).len(),
+            grid: x_count * y_count,
+            fill: musicians as f64 / honeycomb.max(1) as f64,
+        };
+
+        let count = input.musicians.iter().max().map_or(0, |&m| m + 1);
+        let mut multiplicities = vec![0; count];
+        for &m in &input.musicians {
+            multiplicities[m] += 1;
+        }
+        let instruments = Instruments {
+            count,
+            max_multiplicity: multiplicities.iter().copied().max().unwrap_or(0),
+            duplicated: multiplicities.iter().filter(|&&n| n > 1).count(),
+            multiplicities,
+        };
+
+        let all_tastes = || {
+            input
+                .attendees
+                .iter()
+                .flat_map(|a| a.tastes.iter().copied())
+        };
+        let positive = all_tastes().filter(|&t| t > 0.0).count();
+        let negative = all_tastes().filter(|&t| t < 0.0).count();
+        let total = all_tastes().count();
+        let tastes = Tastes {
+            positive,
+            negative,
+            zero: total - positive - negative,
+            negative_ratio: negative as f64 / total.max(1) as f64,
+            attendees_with_negative: input
+                .attendees
+                .iter()
+                .filter(|a| a.tastes.iter().any(|&t| t < 0.0))
+                .count(),
+            min: all_tastes().fold(f64::INFINITY, f64::min),
+            max: all_tastes().fold(f64::NEG_INFINITY, f64::max),
+        };
+
+        let room_area = input.room_width * input.room_height;
+        let stage_center =
+            input.stage_bottom_left + Point::new(input.stage_width / 2.0, input.stage_height / 2.0);
+        let blocked = input
+            .attendees
+            .iter()
+            .filter(|a| {
+                let sight = Line::new(a.pos(), stage_center);
+                input
+                    .pillars
+                    .iter()
+                    .any(|p| p.center.euclidean_distance(&sight) < p.radius)
+            })
+            .count();
+        let pillars = Pillars {
+            count: input.pillars.len(),
+            area_ratio: input
+                .pillars
+                .iter()
+                .map(|p| std::f64::consts::PI * p.radius * p.radius)
+                .sum::<f64>()
+                / room_area,
+            blocked_attendee_ratio: blocked as f64 / input.attendees.len().max(1) as f64,
+        };
+
+        let mut distances: Vec<f64> = input
+            .attendees
+            .iter()
+            .map(|a| distance_to_stage(input, a.pos()))
+            .collect();
+        distances.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let attendees = Attendees {
+            count: distances.len(),
+            min_distance: distances.first().copied().unwrap_or(0.0),
+            median_distance: distances.get(distances.len() / 2).copied().unwrap_or(0.0),
+            mean_distance: distances.iter().sum::<f64>() / distances.len().max(1) as f64,
+            near_stage: NEAR_DISTANCES
+                .iter()
+                .map(|&within| NearStage {
+                    within,
+                    count: distances.iter().filter(|&&d| d <= within).count(),
+                })
+                .collect(),
+        };
+
+        ProblemStats {
+            problem_id,
+            rules: input.rules.to_string(),
+            room_width: input.room_width,
+            room_height: input.room_height,
+            stage_width: input.stage_width,
+            stage_height: input.stage_height,
+            stage_bottom_left: input.stage_bottom_left.x_y(),
+            stage_area_ratio: input.stage_width * input.stage_height / room_area,
+            musicians,
+            capacity,
+            instruments,
+            tastes,
+            pillars,
+            attendees,
+            upper_bound: input.upper_bound(),
+        }
+    }
+
+    // two_stage_exact_togetherとrandom_exact_rectの格子に全員が並ぶか
+    pub fn fits_grid(&self) -> bool {
+        self.capacity.grid >= self.musicians
+    }
+
+    pub fn fits_honeycomb(&self) -> bool {
+        self.capacity.honeycomb >= self.musicians
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn problem_29_stats() {
+        let input = crate::read_input("./testdata/problem-29.json", None).unwrap();
+        let stats = ProblemStats::new(Some(29), &input);
+        assert_eq!(stats.musicians, input.musicians.len());
+        assert_eq!(
+            stats.instruments.multiplicities.iter().sum::<usize>(),
+            stats.musicians
+        );
+        assert_eq!(
+            stats.tastes.positive + stats.tastes.negative + stats.tastes.zero,
+            input.attendees.len() * stats.instruments.count
+        );
+        assert_eq!(stats.pillars.count, 0);
+        assert_eq!(stats.pillars.blocked_attendee_ratio, 0.0);
+        assert!(stats.capacity.honeycomb >= stats.capacity.cartesian);
+        assert!(stats.fits_honeycomb());
+        // 観客はステージの外にいる
+        assert!(stats.attendees.min_distance > 0.0);
+        let near: Vec<_> = stats.attendees.near_stage.iter().map(|n| n.count).collect();
+        assert!(near.windows(2).all(|w| w[0] <= w[1]));
+
+        let json = serde_json::to_string(&stats).unwrap();
+        let parsed: ProblemStats = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.capacity.grid, stats.capacity.grid);
+    }
+
+    #[test]
+    fn pillar_coverage() {
+        let mut input = crate::read_input("./testdata/problem-80.json", None).unwrap();
+        let stats = ProblemStats::new(Some(80), &input);
+        assert!(stats.pillars.count > 0);
+        assert!(stats.pillars.area_ratio > 0.0);
+        input.pillars.clear();
+        assert_eq!(
+            ProblemStats::new(Some(80), &input)
+                .pillars
+                .blocked_attendee_ratio,
+            0.0
+        );
+    }
+}
@@ -1,4 +1,7 @@
+use crate::problem::{Attendee, Input, ScoringRules, Solution};
+use geo::Point;
 use serde::{Deserialize, Serialize};
+use wasm_bindgen::JsValue;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Placement {
@@ -11,3 +14,57 @@ pub struct Pillar {
     pub center: Vec<f64>,
     pub radius: f64,
 }
+
+// JSから渡された問題と解を組み立てる
+pub fn to_input_and_solution(
+    room_width: f64,
+    room_height: f64,
+    stage_width: f64,
+    stage_height: f64,
+    stage_bottom_left: Vec<f64>,
+    musicians: Vec<usize>,
+    attendees: Vec<JsValue>,
+    pillars: Vec<JsValue>,
+    placement: Vec<JsValue>,
+    volumes: Option<Vec<f64>>,
+    is_full: bool,
+) -> (Input, Solution) {
+    let solution = Solution::with_volumes(
+        placement
+            .iter()
+            .map(|p| {
+                let p: Placement = serde_wasm_bindgen::from_value(p.into()).unwrap();
+                Point::new(p.x, p.y)
+            })
+            .collect(),
+        volumes,
+    );
+    let input = Input {
+        room_width,
+        room_height,
+        stage_width,
+        stage_height,
+        stage_bottom_left: Point::new(stage_bottom_left[0], stage_bottom_left[1]),
+        musicians,
+        attendees: attendees
+            .iter()
+            .map(|a| {
+                let a: Attendee = serde_wasm_bindgen::from_value(a.into()).unwrap();
+                a
+            })
+            .collect(),
+        pillars: pillars
+            .iter()
+            .map(|p| {
+                let p: Pillar = serde_wasm_bindgen::from_value(p.into()).unwrap();
+
+                crate::problem::Pillar {
+                    center: Point::new(p.center[0], p.center[1]),
+                    radius: p.radius,
+                }
+            })
+            .collect(),
+        rules: ScoringRules::official(is_full),
+    };
+    (input, solution)
+}
@@ -25,8 +25,8 @@ function run_and_eval() {
     local output="output/${problem/problem/answer}"
     local score="${output/\.json/.score.txt}"
     local submission="${output/\.json/.submission.json}"
-    $BIN/$SOLVER --input $input --output $output
-    $BIN/evaluator --input $input --solution $output | tee $score
+    $BIN/solver solve $SOLVER --input $input --output $output
+    $BIN/solver eval --input $input --solution $output | tee $score
     if [[ "$DRY_RUN" == "false" ]]; then
         curl -X POST -F file=@"${output}" \
             "http://localhost:8080/api/solutions/submit?id=${problem_id}&solver=${SOLVER}%28${SHA:0:5}%29" \
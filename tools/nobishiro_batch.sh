@@ -22,7 +22,7 @@ function run_and_eval() {
     if [[ $problem_id -gt 55 ]]; then
         opts="--sync-effect"
     fi
-    $BIN/$SOLVER --input $input $opts > $output
+    $BIN/solver inspect --input $input --estimate $opts > $output
 }
 
 export -f run_and_eval 
@@ -12,8 +12,9 @@ set -euo pipefail
 
 cd "$(dirname "$0")/.."
 tmp_dir="$(mktemp -d)"
-TOKEN=$(gcloud auth print-access-token)
-optimizer=${OPTIMIZER:-yamanobori_optimizer}
+# `solver serve` で手元のサーバーを使うときは TOKEN=offline を指定してください
+TOKEN=${TOKEN:-$(gcloud auth print-access-token)}
+optimizer=${OPTIMIZER:-yamanobori}
 
 pushd ./solver
 cargo build --release
@@ -31,7 +32,7 @@ for problem_id in "${ids[@]}"; do
   contents="$(echo "$resp" | jq -r '.contents')"
   echo "$contents" > "$tmp_dir/source-$problem_id.json"
   output="$tmp_dir/output-$problem_id.json"
-  "./solver/target/release/$optimizer" --input "problems/problem-$problem_id.json" --solution "$tmp_dir/source-$problem_id.json" --output "$output" || true
+  "./solver/target/release/solver" optimize "$optimizer" --input "problems/problem-$problem_id.json" --solution "$tmp_dir/source-$problem_id.json" --output "$output" || true
   [[ -f "$output" ]] || continue
   curl -X POST -H "Authorization: Bearer ${TOKEN}" -F file=@"$output" "http://localhost:8080/api/solutions/submit?id=${problem_id}&solver=${solver}"
 done
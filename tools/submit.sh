@@ -13,6 +13,7 @@ set -euxo pipefail
 SOLVER=${SOLVER:-default}
 ANSWER_FILE=$(readlink -f "$ANSWER_FILE")
 
-TOKEN=$(gcloud auth print-access-token)
+# `solver serve` で手元のサーバーを使うときは TOKEN=offline を指定してください
+TOKEN=${TOKEN:-$(gcloud auth print-access-token)}
 # curl -X POST -H "Authorization: Bearer ${TOKEN}" -F file=@"${ANSWER_FILE}" "https://icfpc2023.negainoido.com/api/solutions/submit?id=${PROBLEM_ID}&solver=${SOLVER}"
 curl -X POST -H "Authorization: Bearer ${TOKEN}" -F file=@"${ANSWER_FILE}" "http://localhost:8080/api/solutions/submit?id=${PROBLEM_ID}&solver=${SOLVER}"